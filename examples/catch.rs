@@ -0,0 +1,133 @@
+//! A game defined outside of the terminal-games crate: catch the falling drops with the paddle.
+//!
+//! `cargo run --example catch` plays it in the terminal, and
+//! `cargo run --example catch -- window` plays it in a program window.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::rc::Rc;
+use terminal_games::input::{KeyCode, KeyEvent};
+use terminal_games::session::Session;
+use terminal_games::ui::{terminal, window};
+use terminal_games::{
+    App, AppContext, AppStatus, Cell, Graphics, GraphicsBuf, PanelItem, Point, RunConfig, SidePanel,
+};
+
+const WIDTH: u8 = 15;
+const HEIGHT: u8 = 15;
+const PADDLE_WIDTH: i16 = 3;
+const PADDLE: Cell = Cell::Colored((200, 200, 250));
+const DROP: Cell = Cell::Colored((100, 160, 220));
+
+struct Catch {
+    graphics: Graphics,
+    paddle_x: i16,
+    drop: Point,
+    score: u32,
+    missed: bool,
+    rng: StdRng,
+}
+
+impl Catch {
+    fn new(ctx: AppContext) -> Self {
+        let graphics = Graphics::new(
+            "Catch".to_string(),
+            Some(SidePanel {
+                items: vec![
+                    PanelItem::TextItem {
+                        text: "Score: 0".to_string(),
+                    },
+                    PanelItem::TextItem {
+                        text: "A / D: move the paddle".to_string(),
+                    },
+                ],
+            }),
+            GraphicsBuf::new((WIDTH, HEIGHT)),
+        );
+        let mut rng = ctx.rng;
+        let drop = (rng.gen_range(0..WIDTH as i16), 0);
+        let mut this = Self {
+            graphics,
+            paddle_x: (WIDTH as i16 - PADDLE_WIDTH) / 2,
+            drop,
+            score: 0,
+            missed: false,
+            rng,
+        };
+        this.render();
+        this
+    }
+
+    fn render(&mut self) {
+        let buf = &mut self.graphics.buf;
+        for i in 0..WIDTH as usize * HEIGHT as usize {
+            buf.set_by_index(i, Cell::Blank);
+        }
+        for x in self.paddle_x..self.paddle_x + PADDLE_WIDTH {
+            buf.set((x, HEIGHT as i16 - 1), PADDLE);
+        }
+        buf.set(self.drop, DROP);
+
+        let panel = self.graphics.side_panel_mut().unwrap();
+        *panel.unwrap_text_item_mut(0) = format!("Score: {}", self.score);
+    }
+}
+
+impl App for Catch {
+    fn run_frame(&mut self) {
+        if self.missed {
+            return;
+        }
+
+        self.drop.1 += 1;
+        if self.drop.1 == HEIGHT as i16 - 1 {
+            if (self.paddle_x..self.paddle_x + PADDLE_WIDTH).contains(&self.drop.0) {
+                self.score += 1;
+                self.drop = (self.rng.gen_range(0..WIDTH as i16), 0);
+            } else {
+                self.missed = true;
+            }
+        }
+        self.render();
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if !event.is_press() || self.missed {
+            return;
+        }
+        match event.code {
+            KeyCode::Char('a') | KeyCode::Left => self.paddle_x = (self.paddle_x - 1).max(0),
+            KeyCode::Char('d') | KeyCode::Right => {
+                self.paddle_x = (self.paddle_x + 1).min(WIDTH as i16 - PADDLE_WIDTH)
+            }
+            _ => {}
+        }
+        self.render();
+    }
+
+    fn graphics(&self) -> &Graphics {
+        &self.graphics
+    }
+
+    fn status(&self) -> AppStatus {
+        if self.missed {
+            AppStatus::GameOver { score: self.score }
+        } else {
+            AppStatus::Running
+        }
+    }
+}
+
+fn main() {
+    let run_config = RunConfig { frame_rate: 8 };
+    // The session adds quitting with Q, and restarting with R once the game is over
+    let session = Session::new(
+        Rc::new(|ctx| Box::new(Catch::new(ctx))),
+        StdRng::from_entropy(),
+    );
+
+    match std::env::args().nth(1).as_deref() {
+        Some("window") => window::run_main_loop(Box::new(session), run_config.frame_rate),
+        _ => terminal::run_main_loop(Box::new(session), run_config.frame_rate, 3),
+    }
+}
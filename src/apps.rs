@@ -6,15 +6,305 @@ pub mod race;
 pub mod snake;
 pub mod tetris;
 
+use crate::input::KeyEvent;
+use crate::scores::ScoreKind;
+use crate::session::AppConstructor;
 use crate::Graphics;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, Command, FromArgMatches};
+use conway::{Conway, ConwayOptions};
+use noise::{Noise, NoiseOptions};
+use particles::Particles;
+use race::{Race, RaceOptions};
+use rand::rngs::StdRng;
+use snake::{Snake, SnakeOptions};
+use std::rc::Rc;
+use tetris::TetrisOptions;
+
+/// All apps, in the order that they're listed in the launcher
+pub const APPS: &[AppEntry] = &[
+    AppEntry {
+        name: "tetris",
+        description: "Rotate and drop the falling blocks to clear rows",
+        run_config: RunConfig { frame_rate: 30 },
+        augment_args: TetrisOptions::augment_args,
+        constructor: |matches| {
+            let options = TetrisOptions::from_arg_matches(matches)?;
+            options.validate()?;
+            Ok(Rc::new(move |ctx| tetris::new_game(ctx, &options)))
+        },
+    },
+    AppEntry {
+        name: "snake",
+        description: "Eat the food, grow longer, and don't bite yourself",
+        run_config: RunConfig { frame_rate: 10 },
+        augment_args: SnakeOptions::augment_args,
+        constructor: |matches| {
+            with_options(matches, |ctx, options| Box::new(Snake::new(ctx, options)))
+        },
+    },
+    AppEntry {
+        name: "conway",
+        description: "Conway's game of life",
+        run_config: RunConfig { frame_rate: 10 },
+        augment_args: ConwayOptions::augment_args,
+        constructor: |matches| {
+            with_options(matches, |ctx, options| Box::new(Conway::new(ctx, options)))
+        },
+    },
+    AppEntry {
+        name: "noise",
+        description: "Random noise, slowly filling up the screen",
+        run_config: RunConfig { frame_rate: 15 },
+        augment_args: NoiseOptions::augment_args,
+        constructor: |matches| {
+            with_options(matches, |ctx, options| Box::new(Noise::new(ctx, options)))
+        },
+    },
+    AppEntry {
+        name: "race",
+        description: "Steer a car around the track without crashing",
+        run_config: RunConfig { frame_rate: 30 },
+        augment_args: RaceOptions::augment_args,
+        constructor: |matches| {
+            with_options(matches, |ctx, options| Box::new(Race::new(ctx, options)))
+        },
+    },
+    AppEntry {
+        name: "particles",
+        description: "A tiny simulation of falling liquid",
+        run_config: RunConfig { frame_rate: 5 },
+        augment_args: |command| command,
+        constructor: |_| Ok(Rc::new(|ctx| Box::new(Particles::new(ctx)))),
+    },
+];
+
+pub fn find_app(name: &str) -> Option<&'static AppEntry> {
+    APPS.iter().find(|entry| entry.name == name)
+}
+
+pub struct AppEntry {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run_config: RunConfig,
+    /// Adds the app's own command-line options
+    pub augment_args: fn(Command) -> Command,
+    /// Reads the app's options, and returns a constructor that builds the app with them
+    pub constructor: fn(&ArgMatches) -> Result<AppConstructor, clap::Error>,
+}
+
+impl AppEntry {
+    /// The app's command-line interface, to be used as a subcommand
+    pub fn command(&self) -> Command {
+        (self.augment_args)(Command::new(self.name).about(self.description))
+    }
+
+    /// Parses the app's options from the arguments that follow its name on the command line
+    pub fn parse_args(&self, args: &[String]) -> Result<AppConstructor, clap::Error> {
+        let args = std::iter::once(self.name).chain(args.iter().map(|arg| &arg[..]));
+        let matches = self.command().try_get_matches_from(args)?;
+        (self.constructor)(&matches)
+    }
+}
+
+fn with_options<O: FromArgMatches + 'static>(
+    matches: &ArgMatches,
+    new: fn(AppContext, &O) -> Box<dyn App>,
+) -> Result<AppConstructor, clap::Error> {
+    let options = O::from_arg_matches(matches)?;
+    Ok(Rc::new(move |ctx| new(ctx, &options)))
+}
+
+/// The arguments that were explicitly given to an app on the command line, such that they
+/// can be parsed again with [AppEntry::parse_args].
+pub fn explicit_args(command: &Command, matches: &ArgMatches) -> Vec<String> {
+    let mut args = vec![];
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        if arg.get_action().takes_values() {
+            for value in matches.get_raw(id).unwrap() {
+                args.push(format!("--{}", long));
+                args.push(value.to_string_lossy().to_string());
+            }
+        } else {
+            args.push(format!("--{}", long));
+        }
+    }
+    args
+}
+
+/// Joins arguments into one line, such that [split_args] gives them back. Arguments with
+/// whitespace, quotes or backslashes in them are put in double quotes, with any quotes,
+/// backslashes, tabs and newlines escaped by a backslash.
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn quote_arg(arg: &str) -> String {
+    let needs_quotes =
+        arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\');
+    if !needs_quotes {
+        return arg.to_string();
+    }
+    let mut quoted = "\"".to_string();
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Splits a line that was written by [join_args] back into the arguments
+pub fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(first) = chars.next() else {
+            break;
+        };
+        let mut arg = String::new();
+        if first == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('t') => arg.push('\t'),
+                        Some('n') => arg.push('\n'),
+                        Some(c) => arg.push(c),
+                        None => return Err(format!("Unfinished escape in {:?}", line)),
+                    },
+                    Some(c) => arg.push(c),
+                    None => return Err(format!("Unfinished quote in {:?}", line)),
+                }
+            }
+        } else {
+            arg.push(first);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
 
 pub trait App {
     fn run_frame(&mut self);
-    fn handle_pressed_key(&mut self, _key: char) {}
-    fn handle_released_key(&mut self, _key: char) {}
+    fn handle_key_event(&mut self, _event: KeyEvent) {}
     fn graphics(&self) -> &Graphics;
+    fn status(&self) -> AppStatus {
+        AppStatus::Running
+    }
+    /// Overrides the frame rate that the app was constructed with
+    fn frame_rate(&self) -> Option<u32> {
+        None
+    }
+    /// What the score in [AppStatus::GameOver] and [AppStatus::Won] measures
+    fn score_kind(&self) -> ScoreKind {
+        ScoreKind::Points
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AppStatus {
+    Running,
+    /// A score of 0 means that there is nothing to put on the high score table, for example
+    /// if a timed goal was never reached.
+    GameOver {
+        score: u32,
+    },
+    /// Like [AppStatus::GameOver], but the game ended because the player beat it
+    Won {
+        score: u32,
+    },
+    /// The runtime should shut down (or return to wherever the app was launched from)
+    RequestQuit,
+}
+
+impl AppStatus {
+    /// The final score, once the game has ended one way or another
+    pub fn final_score(self) -> Option<u32> {
+        match self {
+            AppStatus::GameOver { score } | AppStatus::Won { score } => Some(score),
+            AppStatus::Running | AppStatus::RequestQuit => None,
+        }
+    }
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct RunConfig {
     pub frame_rate: u32,
 }
+
+/// Everything an app is handed when it's constructed.
+pub struct AppContext {
+    /// All randomness in an app must come from here, so that a run can be reproduced from its seed.
+    pub rng: StdRng,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_app_runs_with_default_options() {
+        for entry in APPS {
+            let constructor = entry.parse_args(&[]).unwrap();
+            let mut app = constructor(AppContext {
+                rng: StdRng::seed_from_u64(0),
+            });
+            for _ in 0..300 {
+                app.run_frame();
+            }
+            assert_ne!(app.status(), AppStatus::RequestQuit, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn args_can_be_split_back() {
+        let cases: &[&[&str]] = &[
+            &[],
+            &["--width", "40"],
+            &["--level", "my level.txt"],
+            &[
+                "",
+                "tab\there",
+                "new\nline",
+                "\"quoted\"",
+                "back\\slash",
+                "  ",
+            ],
+        ];
+        for &args in cases {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let line = join_args(&args);
+            assert_eq!(split_args(&line), Ok(args), "{:?}", line);
+            assert!(!line.contains(['\t', '\n']));
+        }
+    }
+
+    #[test]
+    fn unfinished_quotes_are_rejected() {
+        assert!(split_args("--level \"my level").is_err());
+        assert!(split_args("\"ends with \\").is_err());
+    }
+}
@@ -1,5 +1,6 @@
-use crate::apps::RunConfig;
+use crate::apps::AppContext;
 use crate::{App, Cell, Graphics, GraphicsBuf, PanelItem, Point, SidePanel};
+use std::cmp::max;
 
 pub struct Conway {
     dimensions: (u8, u8),
@@ -7,29 +8,48 @@ pub struct Conway {
     tmp_buf: GraphicsBuf,
 }
 
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConwayOptions {
+    /// Width of the world
+    #[arg(long, default_value = "20", value_parser = clap::value_parser!(u8).range(1..))]
+    width: u8,
+
+    /// Height of the world
+    #[arg(long, default_value = "20", value_parser = clap::value_parser!(u8).range(1..))]
+    height: u8,
+
+    /// A file with the starting pattern, in the RLE format. It's placed in the middle of the world.
+    #[arg(long, value_parser = Pattern::load)]
+    pattern: Option<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    live_cells: Vec<Point>,
+    dimensions: (i16, i16),
+}
+
 impl Conway {
-    pub fn new() -> (Self, RunConfig) {
-        let dimensions = (20, 20);
-        let cells_offset = (10, 0);
-        let live_cells = [
-            (2, 3),
-            (3, 3),
-            (4, 3),
-            (5, 3),
-            (3, 4),
-            (4, 4),
-            (5, 4),
-            (6, 4),
-            (8, 1),
-            (9, 1),
-            (8, 2),
-            (9, 2),
-        ];
+    pub fn new(_ctx: AppContext, options: &ConwayOptions) -> Self {
+        let dimensions = (options.width, options.height);
+        let (cells_offset, live_cells) = match &options.pattern {
+            Some(pattern) => (
+                (
+                    (dimensions.0 as i16 - pattern.dimensions.0) / 2,
+                    (dimensions.1 as i16 - pattern.dimensions.1) / 2,
+                ),
+                pattern.live_cells.clone(),
+            ),
+            None => ((10, 0), Self::default_live_cells()),
+        };
 
         let mut buf0 = GraphicsBuf::new(dimensions);
         for cell in &live_cells {
             let cell = (cell.0 + cells_offset.0, cell.1 + cells_offset.1);
-            buf0.set(cell, Cell::filled());
+            // Any part of the pattern that doesn't fit in the world is cut off
+            if buf0.get(cell).is_some() {
+                buf0.set(cell, Cell::filled());
+            }
         }
         let tmp_buf = GraphicsBuf::new(dimensions);
 
@@ -40,16 +60,29 @@ impl Conway {
         });
 
         let graphics = Graphics::new("Conway".to_string(), side_panel, buf0);
-        let run_config = RunConfig { frame_rate: 10 };
-
-        (
-            Self {
-                dimensions,
-                graphics,
-                tmp_buf,
-            },
-            run_config,
-        )
+
+        Self {
+            dimensions,
+            graphics,
+            tmp_buf,
+        }
+    }
+
+    fn default_live_cells() -> Vec<Point> {
+        vec![
+            (2, 3),
+            (3, 3),
+            (4, 3),
+            (5, 3),
+            (3, 4),
+            (4, 4),
+            (5, 4),
+            (6, 4),
+            (8, 1),
+            (9, 1),
+            (8, 2),
+            (9, 2),
+        ]
     }
 
     fn count_live_neighbors(&self, position: Point) -> u32 {
@@ -128,3 +161,66 @@ impl App for Conway {
         &self.graphics
     }
 }
+
+impl Pattern {
+    /// Loads a pattern in the RLE format, where for example a glider is written as:
+    ///
+    /// ```text
+    /// #C A comment
+    /// x = 3, y = 3
+    /// bo$2bo$3o!
+    /// ```
+    fn load(path: &str) -> Result<Self, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+
+        let mut live_cells = vec![];
+        let (mut x, mut y) = (0, 0);
+        let mut max_x = 0;
+        let mut run_count: Option<i16> = None;
+        let mut passed_header = false;
+
+        'lines: for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+            if !passed_header {
+                // The header specifies the size, which we'll figure out from the cells anyway
+                passed_header = true;
+                if line.starts_with('x') {
+                    continue;
+                }
+            }
+            for ch in line.chars() {
+                if let Some(digit) = ch.to_digit(10) {
+                    run_count = Some(run_count.unwrap_or(0) * 10 + digit as i16);
+                    continue;
+                }
+                let count = run_count.take().unwrap_or(1);
+                match ch {
+                    'b' | '.' => x += count,
+                    '$' => {
+                        y += count;
+                        x = 0;
+                    }
+                    '!' => break 'lines,
+                    ch if ch.is_alphabetic() => {
+                        for _ in 0..count {
+                            live_cells.push((x, y));
+                            x += 1;
+                        }
+                    }
+                    ch if ch.is_whitespace() => {}
+                    unexpected => return Err(format!("Unexpected {:?} in {}", unexpected, path)),
+                }
+                max_x = max(max_x, x);
+            }
+        }
+
+        Ok(Self {
+            live_cells,
+            dimensions: (max_x, y + 1),
+        })
+    }
+}
@@ -1,36 +1,46 @@
-use crate::apps::RunConfig;
+use crate::apps::{AppContext, AppStatus};
 use crate::{App, Cell, Graphics, GraphicsBuf};
+use rand::rngs::StdRng;
 use rand::Rng;
 
+#[derive(clap::Args, Debug, Clone)]
+pub struct NoiseOptions {
+    /// Width of the screen
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u8).range(1..))]
+    width: u8,
+
+    /// Height of the screen
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u8).range(1..))]
+    height: u8,
+}
+
 pub struct Noise {
     graphics: Graphics,
     empty_indices: Vec<usize>,
+    rng: StdRng,
 }
 
 impl Noise {
-    pub fn new() -> (Self, RunConfig) {
-        let dimensions = (10, 5);
+    pub fn new(ctx: AppContext, options: &NoiseOptions) -> Self {
+        let dimensions = (options.width, options.height);
         let buf = GraphicsBuf::new(dimensions);
         let mut empty_indices = vec![];
         for i in 0..buf.buf.len() {
-            empty_indices.push(i as usize);
+            empty_indices.push(i);
         }
         let graphics = Graphics::new("Noise".to_string(), None, buf);
-        let run_config = RunConfig { frame_rate: 15 };
-        (
-            Self {
-                graphics,
-                empty_indices,
-            },
-            run_config,
-        )
+        Self {
+            graphics,
+            empty_indices,
+            rng: ctx.rng,
+        }
     }
 }
 
 impl App for Noise {
     fn run_frame(&mut self) {
         if !self.empty_indices.is_empty() {
-            let i = rand::thread_rng().gen_range(0..self.empty_indices.len());
+            let i = self.rng.gen_range(0..self.empty_indices.len());
             let buf_index = self.empty_indices[i];
             self.graphics.buf.set_by_index(buf_index, Cell::filled());
             self.empty_indices.swap_remove(i);
@@ -44,4 +54,13 @@ impl App for Noise {
     fn graphics(&self) -> &Graphics {
         &self.graphics
     }
+
+    fn status(&self) -> AppStatus {
+        if self.empty_indices.is_empty() {
+            // Every run fills the whole screen, so there's no score worth keeping
+            AppStatus::GameOver { score: 0 }
+        } else {
+            AppStatus::Running
+        }
+    }
 }
@@ -1,6 +1,8 @@
-use crate::apps::RunConfig;
+use crate::apps::AppContext;
+use crate::input::{KeyCode, KeyEvent};
 use crate::{App, Cell, Color, Graphics, GraphicsBuf, PanelItem, SidePanel};
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::cmp::{max, min};
 
@@ -12,6 +14,7 @@ pub struct Particles {
     particles: Vec<Particle>,
     spawn_rate: f64,
     spawn_velocity: [i16; 2],
+    rng: StdRng,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -22,7 +25,7 @@ struct Particle {
 }
 
 impl Particles {
-    pub fn new() -> (Self, RunConfig) {
+    pub fn new(ctx: AppContext) -> Self {
         let dimensions = (30, 30);
         let mut buf = GraphicsBuf::new(dimensions);
 
@@ -79,17 +82,14 @@ impl Particles {
             }),
             buf,
         );
-        let run_config = RunConfig { frame_rate: 5 };
-        (
-            Self {
-                graphics,
-                frame: 0,
-                particles,
-                spawn_rate: 0.1,
-                spawn_velocity: [1, 0],
-            },
-            run_config,
-        )
+        Self {
+            graphics,
+            frame: 0,
+            particles,
+            spawn_rate: 0.1,
+            spawn_velocity: [1, 0],
+            rng: ctx.rng,
+        }
     }
 
     fn is_free(buf: &GraphicsBuf, position: (i16, i16)) -> bool {
@@ -175,11 +175,11 @@ impl App for Particles {
             if Self::is_free(buf, (x, y + 1)) {
                 // Gravity (straight down)
                 particle.velocity[1] += 1;
-                if rand::thread_rng().gen_bool(0.1) {
+                if self.rng.gen_bool(0.1) {
                     // to make things look less static
                     particle.velocity[1] += 1;
                 }
-            } else if i16::abs(particle.velocity[0]) > 0 && rand::thread_rng().gen_bool(0.5) {
+            } else if i16::abs(particle.velocity[0]) > 0 && self.rng.gen_bool(0.5) {
                 // Friction
                 particle.velocity[0] =
                     (particle.velocity[0].abs() - 1) * particle.velocity[0].signum();
@@ -188,7 +188,7 @@ impl App for Particles {
             if particle.velocity == [0, 0] {
                 // Gravity (diagonally)
                 let mut falling = false;
-                for &dx in [[-1, 1], [1, -1]].choose(&mut rand::thread_rng()).unwrap() {
+                for &dx in [[-1, 1], [1, -1]].choose(&mut self.rng).unwrap() {
                     if Self::is_free(buf, (x + dx, y + 1)) {
                         particle.velocity = [dx, 1];
                         falling = true;
@@ -208,8 +208,8 @@ impl App for Particles {
                         .unwrap_or(false)
                     {
                         // If above liquid, sometimes move side-way by chance
-                        if rand::thread_rng().gen_bool(0.2) {
-                            let dx = *[-1, 1].choose(&mut rand::thread_rng()).unwrap();
+                        if self.rng.gen_bool(0.2) {
+                            let dx = *[-1, 1].choose(&mut self.rng).unwrap();
                             if Self::is_free(buf, (x + dx, y)) {
                                 particle.velocity[0] = dx;
                             }
@@ -221,12 +221,10 @@ impl App for Particles {
 
         // SPAWN NEW
         // ---------
-        if rand::thread_rng().gen_bool(self.spawn_rate) {
-            let position = *[[0, 1], [0, 0], [0, 2]]
-                .choose(&mut rand::thread_rng())
-                .unwrap();
+        if self.rng.gen_bool(self.spawn_rate) {
+            let position = *[[0, 1], [0, 0], [0, 2]].choose(&mut self.rng).unwrap();
             let color = *[(100, 160, 220), (120, 120, 250), (150, 150, 250)]
-                .choose(&mut rand::thread_rng())
+                .choose(&mut self.rng)
                 .unwrap();
             let particle = Particle {
                 color,
@@ -240,22 +238,29 @@ impl App for Particles {
         self.update_info_text()
     }
 
-    fn handle_pressed_key(&mut self, key: char) {
-        match key {
-            'w' => {
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if !event.is_press() {
+            return;
+        }
+        match event.code {
+            KeyCode::Char('w') | KeyCode::Up => {
                 self.spawn_rate += 0.05;
                 if self.spawn_rate > 1.0 {
                     self.spawn_rate = 1.0;
                 }
             }
-            's' => {
+            KeyCode::Char('s') | KeyCode::Down => {
                 self.spawn_rate -= 0.05;
                 if self.spawn_rate < 0.0 {
                     self.spawn_rate = 0.0;
                 }
             }
-            'a' => self.spawn_velocity[0] = max(self.spawn_velocity[0] - 1, 1),
-            'd' => self.spawn_velocity[0] = min(self.spawn_velocity[0] + 1, 10),
+            KeyCode::Char('a') | KeyCode::Left => {
+                self.spawn_velocity[0] = max(self.spawn_velocity[0] - 1, 1)
+            }
+            KeyCode::Char('d') | KeyCode::Right => {
+                self.spawn_velocity[0] = min(self.spawn_velocity[0] + 1, 10)
+            }
             _ => {}
         }
     }
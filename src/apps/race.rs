@@ -1,4 +1,5 @@
-use crate::apps::RunConfig;
+use crate::apps::{AppContext, AppStatus};
+use crate::input::{KeyCode, KeyEvent};
 use crate::{App, Cell, Graphics, GraphicsBuf, PanelItem, Point, SidePanel};
 use std::cmp::{max, min};
 use std::fs::File;
@@ -15,6 +16,14 @@ pub struct Race {
     elapsed_time: u32,
 }
 
+#[derive(clap::Args, Debug, Clone)]
+pub struct RaceOptions {
+    /// A file with the race track. 'x' is a wall, '.' is grass and 'o' is where the car starts.
+    #[arg(long, default_value = "src/apps/race_map.txt", value_parser = World::load)]
+    map: World,
+}
+
+#[derive(Debug, Clone)]
 struct World {
     dimensions: (u16, u16),
     car: Point,
@@ -28,8 +37,8 @@ const GRASS: Cell = Cell::Colored((100, 150, 100));
 const OBSTACLE: Cell = Cell::Colored((100, 100, 150));
 
 impl Race {
-    pub fn new() -> (Self, RunConfig) {
-        let world = Self::load_map();
+    pub fn new(_ctx: AppContext, options: &RaceOptions) -> Self {
+        let world = options.map.clone();
 
         let buf = GraphicsBuf::new((30, 30));
 
@@ -66,13 +75,12 @@ impl Race {
                         buf: minimap_buf,
                     },
                     PanelItem::TextItem {
-                        text: "Use WASD to control the car.\nThe blinking dot indicates where you are heading.".to_string(),
+                        text: "Use WASD or the arrow keys to control the car.\nThe blinking dot indicates where you are heading.".to_string(),
                     },
                 ],
             }),
             buf,
         );
-        let run_config = RunConfig { frame_rate: 30 };
 
         let mut this = Self {
             graphics,
@@ -85,49 +93,13 @@ impl Race {
             elapsed_time,
         };
         this.update_graphics();
-        (this, run_config)
+        this
     }
 
     fn time_text(elapsed_time: u32) -> String {
         format!("Time: {}", elapsed_time)
     }
 
-    fn load_map() -> World {
-        let mut car_pos_in_world = None;
-        let mut obstacles = vec![];
-        let mut grass = vec![];
-
-        let file = File::open("src/apps/race_map.txt").unwrap();
-
-        let reader = BufReader::new(file);
-        let lines = reader.lines();
-        let mut y = 0;
-        let mut max_x = 0;
-        for line in lines {
-            for (x, ch) in line.unwrap().chars().enumerate() {
-                let x = x as i16;
-                if ch == 'x' {
-                    obstacles.push((x, y));
-                } else if ch == 'o' {
-                    let previous = car_pos_in_world.replace((x, y));
-                    assert_eq!(previous, None);
-                } else if ch == '.' {
-                    grass.push((x, y))
-                }
-                max_x = max(max_x, x);
-            }
-            y += 1;
-        }
-        let car_pos_in_world = car_pos_in_world.expect("Must specify car position");
-
-        World {
-            car: car_pos_in_world,
-            obstacles,
-            grass,
-            dimensions: (max_x as u16, (y - 1) as u16),
-        }
-    }
-
     fn update_graphics(&mut self) {
         for i in
             0..self.graphics.buf.dimensions().0 as usize * self.graphics.buf.dimensions().1 as usize
@@ -202,6 +174,50 @@ impl Race {
     }
 }
 
+impl World {
+    fn load(path: &str) -> Result<Self, String> {
+        let mut car_pos_in_world = None;
+        let mut obstacles = vec![];
+        let mut grass = vec![];
+
+        let file = File::open(path).map_err(|e| format!("Couldn't open {}: {}", path, e))?;
+
+        let reader = BufReader::new(file);
+        let lines = reader.lines();
+        let mut y = 0;
+        let mut max_x = 0;
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            for (x, ch) in line.chars().enumerate() {
+                let x = x as i16;
+                if ch == 'x' {
+                    obstacles.push((x, y));
+                } else if ch == 'o' {
+                    if car_pos_in_world.replace((x, y)).is_some() {
+                        return Err("There can only be one car position ('o')".to_string());
+                    }
+                } else if ch == '.' {
+                    grass.push((x, y))
+                }
+                max_x = max(max_x, x);
+            }
+            y += 1;
+        }
+        let car_pos_in_world =
+            car_pos_in_world.ok_or_else(|| "Must specify car position ('o')".to_string())?;
+        if max_x < 1 || y < 2 {
+            return Err("The map is too small".to_string());
+        }
+
+        Ok(World {
+            car: car_pos_in_world,
+            obstacles,
+            grass,
+            dimensions: (max_x as u16, (y - 1) as u16),
+        })
+    }
+}
+
 impl App for Race {
     fn run_frame(&mut self) {
         self.timer = (self.timer + 1) % 8;
@@ -241,7 +257,7 @@ impl App for Race {
                         .as_mut()
                         .unwrap()
                         .unwrap_text_item_mut(3);
-                    *text = "Game Over:\nYou crashed!".to_string();
+                    *text = "You crashed!".to_string();
                     break;
                 }
             }
@@ -254,13 +270,25 @@ impl App for Race {
         self.update_graphics();
     }
 
-    fn handle_pressed_key(&mut self, key: char) {
-        self.cursor.handle_pressed_key(key);
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if event.is_press() {
+            self.cursor.handle_pressed_key(event.code);
+        }
     }
 
     fn graphics(&self) -> &Graphics {
         &self.graphics
     }
+
+    fn status(&self) -> AppStatus {
+        if self.crashed {
+            AppStatus::GameOver {
+                score: self.elapsed_time,
+            }
+        } else {
+            AppStatus::Running
+        }
+    }
 }
 
 struct Cursor {
@@ -282,12 +310,16 @@ impl Cursor {
         self.timer = (self.timer + 1) % 10;
     }
 
-    fn handle_pressed_key(&mut self, key: char) {
+    fn handle_pressed_key(&mut self, key: KeyCode) {
         match key {
-            'w' => self.direction[1] = max(-1, self.direction[1] - 1),
-            'a' => self.direction[0] = max(-1, self.direction[0] - 1),
-            's' => self.direction[1] = min(self.direction[1] + 1, 1),
-            'd' => self.direction[0] = min(self.direction[0] + 1, 1),
+            KeyCode::Char('w') | KeyCode::Up => self.direction[1] = max(-1, self.direction[1] - 1),
+            KeyCode::Char('a') | KeyCode::Left => {
+                self.direction[0] = max(-1, self.direction[0] - 1)
+            }
+            KeyCode::Char('s') | KeyCode::Down => self.direction[1] = min(self.direction[1] + 1, 1),
+            KeyCode::Char('d') | KeyCode::Right => {
+                self.direction[0] = min(self.direction[0] + 1, 1)
+            }
             _ => {}
         }
     }
@@ -1,151 +1,697 @@
-use crate::apps::RunConfig;
+use crate::apps::{AppContext, AppStatus};
+use crate::input::{KeyCode, KeyEvent};
+use crate::scores;
 use crate::{translated, Cell, Graphics, GraphicsBuf, PanelItem, SidePanel};
 use crate::{App, Color, Direction, Point};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::str::FromStr;
 
 pub struct Snake {
-    game_size: (u8, u8),
+    /// Played in order, moving on to the next one once the current one's goal is reached
+    levels: Vec<Level>,
+    level_index: usize,
     alive: bool,
-    snake: Vec<Point>,
+    /// Whether the snake filled the board, or reached the last level's goal
+    won: bool,
+    /// From the tail to the head
+    snake: VecDeque<Point>,
+    free_cells: FreeCells,
     direction: Direction,
-    food: Point,
+    /// Turns that were pressed but not taken yet. The snake takes one per step, so that
+    /// quick turns in a row aren't lost in between two steps.
+    turns: VecDeque<Direction>,
+    /// None once there's nowhere left to put it
+    food: Option<Point>,
     graphics: Graphics,
     score: u32,
+    /// How much food has been eaten on the current level
+    level_score: u32,
+    /// How long the game has been going, counted in steps so that it doesn't depend on how
+    /// fast the frames actually run
+    elapsed_millis: u32,
+    /// Steps per second
+    speed: u32,
+    /// How fast the snake can get by speeding up, if it does
+    max_speed: Option<u32>,
+    rng: StdRng,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SnakeOptions {
+    /// Width of the board, unless playing levels
+    #[arg(long, default_value = "30", value_parser = clap::value_parser!(u8).range(3..))]
+    width: u8,
+
+    /// Height of the board, unless playing levels
+    #[arg(long, default_value = "20", value_parser = clap::value_parser!(u8).range(3..))]
+    height: u8,
+
+    /// Leaving the board on one side comes back in on the other, unless playing levels
+    #[arg(long)]
+    wrap: bool,
+
+    /// A file with a level to play instead of the empty board. Given more than once, the
+    /// levels are played in order. See src/apps/snake_levels for some examples.
+    #[arg(long = "level", value_parser = Level::load)]
+    levels: Vec<Level>,
+
+    /// How fast the snake is, and how fast it can get
+    #[arg(long, value_enum, default_value = "normal")]
+    difficulty: Difficulty,
+
+    /// How many steps the snake takes per second at first, instead of the difficulty's
+    /// speed
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    speed: Option<u32>,
+
+    /// Take one more step per second for every few pieces of food, up to the
+    /// difficulty's top speed
+    #[arg(long)]
+    speed_up: bool,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Difficulty {
+    /// 6 steps per second, speeding up to at most 12
+    Easy,
+    /// 10 steps per second, speeding up to at most 20
+    Normal,
+    /// 15 steps per second, speeding up to at most 30
+    Hard,
+}
+
+impl Difficulty {
+    fn speed(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 6,
+            Difficulty::Normal => 10,
+            Difficulty::Hard => 15,
+        }
+    }
+
+    fn top_speed(&self) -> u32 {
+        self.speed() * 2
+    }
+}
+
+/// A board to play on.
+///
+/// In a level file, 'x' is a wall, '.' or ' ' is empty, and one of '>', '<', '^' and 'v'
+/// is where the snake starts and which way it goes. Each digit marks a portal, and has to
+/// appear exactly twice: going into one end comes out of the other, and right into the
+/// next portal if there's one in the way. The grid can be preceded by lines with settings:
+/// "wrap", to come back in on the other side when leaving the board, and "goal <food>", to
+/// move on to the next level after eating that much. The game is won by reaching the last
+/// level's goal, or by filling the whole board.
+#[derive(Debug, Clone)]
+pub struct Level {
+    dimensions: (u8, u8),
+    /// Row by row
+    tiles: Vec<Tile>,
+    wrap: bool,
+    start: Point,
+    start_direction: Direction,
+    goal: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Open,
+    Wall,
+    /// Leads to the other end of the portal, at this point
+    Portal(Point),
+}
+
+/// Keeps track of the cells that neither the snake nor the level takes up, such that
+/// checking a cell, taking or freeing it, and picking a random free one are all quick
+/// regardless of the size of the board
+#[derive(Debug, Clone)]
+struct FreeCells {
+    width: usize,
+    /// In no particular order
+    free: Vec<Point>,
+    /// Where in `free` each cell is, row by row, or None if it's taken
+    indices: Vec<Option<usize>>,
 }
 
 const SNAKE_COLOR: Color = (255, 255, 100);
 const FOOD_COLOR: Color = (255, 100, 100);
+const WALL_COLOR: Color = (100, 100, 150);
+const PORTAL_COLOR: Color = (100, 200, 255);
+const WINNING_SNAKE_COLOR: Color = (100, 255, 100);
+/// How much food it takes to speed up by one step per second
+const FOOD_PER_SPEED_UP: u32 = 3;
+/// Any turns pressed beyond this many steps ahead are ignored
+const MAX_QUEUED_TURNS: usize = 3;
 
 impl Snake {
-    pub fn new() -> (Self, RunConfig) {
-        let game_size: (u8, u8) = (30, 20);
-        let snake_pos: Point = (1, 5);
-        let snake = vec![snake_pos];
-
-        let mut buf = GraphicsBuf::new(game_size);
-        let direction = Direction::Right;
-        buf.set(snake_pos, Cell::Colored(SNAKE_COLOR));
+    pub fn new(ctx: AppContext, options: &SnakeOptions) -> Self {
+        let levels = if options.levels.is_empty() {
+            vec![Level::empty((options.width, options.height), options.wrap)]
+        } else {
+            options.levels.clone()
+        };
 
-        let help_text = "Use WASD keys to control the snake!".to_string();
+        let free_cells = FreeCells::new(&levels[0]);
+        let speed = options.speed.unwrap_or(options.difficulty.speed());
 
-        let score = 0;
+        let help_text = "Use WASD or the arrow keys to control the snake!".to_string();
         let graphics = Graphics::new(
             "Snake".to_string(),
             Some(SidePanel {
                 items: vec![
                     PanelItem::TextItem {
-                        text: format!("Score: {}", score),
+                        text: "".to_string(),
                     },
                     PanelItem::TextItem { text: help_text },
                 ],
             }),
-            buf,
+            GraphicsBuf::new(levels[0].dimensions),
         );
 
         let mut this = Self {
-            game_size,
+            levels,
+            level_index: 0,
             alive: true,
-            snake,
-            direction,
-            food: (3, 5),
+            won: false,
+            snake: VecDeque::new(),
+            free_cells,
+            direction: Direction::Right,
+            turns: VecDeque::new(),
+            food: None,
             graphics,
-            score,
+            score: 0,
+            level_score: 0,
+            elapsed_millis: 0,
+            speed,
+            max_speed: options
+                .speed_up
+                .then(|| speed.max(options.difficulty.top_speed())),
+            rng: ctx.rng,
         };
+        this.start_level(0);
+        this
+    }
 
-        let food = this.pick_new_food_location();
-        this.food = food;
-        this.graphics.buf.set(food, Cell::Colored(FOOD_COLOR));
-        (this, RunConfig { frame_rate: 10 })
+    fn level(&self) -> &Level {
+        &self.levels[self.level_index]
     }
 
-    fn set_direction(&mut self, direction: Direction) {
-        if self.snake.len() >= 2 {
-            let neck = self.snake[self.snake.len() - 2];
-            if translated(*self.snake.last().unwrap(), direction) == neck {
-                return;
+    /// Puts a new snake at the start of the level, and draws the level from scratch
+    fn start_level(&mut self, index: usize) {
+        self.level_index = index;
+        self.level_score = 0;
+        let level = &self.levels[index];
+        self.snake = VecDeque::from([level.start]);
+        self.free_cells = FreeCells::new(level);
+        self.free_cells.take(level.start);
+        self.direction = level.start_direction;
+        self.turns.clear();
+
+        self.graphics.buf = GraphicsBuf::new(level.dimensions);
+        for y in 0..level.dimensions.1 as i16 {
+            for x in 0..level.dimensions.0 as i16 {
+                let cell = match level.tile((x, y)) {
+                    Tile::Open => Cell::Blank,
+                    Tile::Wall => Cell::Colored(WALL_COLOR),
+                    Tile::Portal(_) => Cell::Colored(PORTAL_COLOR),
+                };
+                self.graphics.buf.set((x, y), cell);
             }
         }
-        self.direction = direction;
+        self.graphics
+            .buf
+            .set(level.start, Cell::Colored(SNAKE_COLOR));
+
+        self.place_food();
+        self.update_side_panel();
     }
 
-    fn is_within_game_bounds(&self, point: Point) -> bool {
-        point.0 >= 0
-            && point.1 >= 0
-            && point.0 < self.game_size.0 as i16
-            && point.1 < self.game_size.1 as i16
+    fn update_side_panel(&mut self) {
+        let mut text = format!("Score: {}\nSpeed: {}", self.score, self.speed);
+        if self.levels.len() > 1 {
+            text.push_str(&format!(
+                "\nLevel: {}/{}",
+                self.level_index + 1,
+                self.levels.len()
+            ));
+        }
+        if let Some(goal) = self.level().goal {
+            text.push_str(&format!("\nFood: {}/{}", self.level_score, goal));
+        }
+        if self.won || !self.alive {
+            let result = if self.won { "You win!" } else { "Game over" };
+            text.push_str(&format!(
+                "\n\n{}\nLength: {}\nTime: {}",
+                result,
+                self.snake.len(),
+                scores::format_time(self.elapsed_millis)
+            ));
+        }
+        *self
+            .graphics
+            .side_panel_mut()
+            .unwrap()
+            .unwrap_text_item_mut(0) = text;
     }
 
-    fn pick_new_food_location(&self) -> Point {
-        let mut food_candidates: Vec<Point> = vec![];
-        for x in 0..self.game_size.0 {
-            for y in 0..self.game_size.1 {
-                let x = x as i16;
-                let y = y as i16;
-                if !self.snake.contains(&(x, y)) {
-                    food_candidates.push((x, y))
-                }
+    fn queue_turn(&mut self, direction: Direction) {
+        // Going the same way again, or doubling back on the previous turn, would be wasted
+        let previous = self.turns.back().copied().unwrap_or(self.direction);
+        if direction == previous
+            || (self.snake.len() >= 2 && direction == previous.opposite())
+            || self.turns.len() >= MAX_QUEUED_TURNS
+        {
+            return;
+        }
+        self.turns.push_back(direction);
+    }
+
+    /// Takes the next queued turn, unless it would run the snake straight back into itself
+    fn take_turn(&mut self) {
+        let Some(direction) = self.turns.pop_front() else {
+            return;
+        };
+        if self.snake.len() >= 2 && direction == self.direction.opposite() {
+            return;
+        }
+        self.direction = direction;
+    }
+
+    /// Where the head goes next, going through portals and wrapping around the board. None
+    /// if it runs into a wall or off the board, or if portals lead into each other in a loop.
+    fn next_head(&self, head: Point) -> Option<Point> {
+        let level = self.level();
+        let mut next = level.wrapped(translated(head, self.direction))?;
+        // Every portal can be gone through at most once, or the snake would be going in circles
+        let mut portals_left = level.tiles.len();
+        while let Some(exit) = level.portal_exit(next) {
+            if portals_left == 0 {
+                return None;
             }
+            portals_left -= 1;
+            // Come out on the far side of the other end, still going the same way. That
+            // might be yet another portal.
+            next = level.wrapped(translated(exit, self.direction))?;
+        }
+        if level.tile(next) == Tile::Wall {
+            return None;
         }
+        Some(next)
+    }
 
-        *food_candidates
-            .choose(&mut rand::thread_rng())
-            .expect("Vacant food location")
+    /// A random cell that neither the snake nor the level takes up, to put something on.
+    /// There might not be any, once the snake is long enough.
+    fn pick_free_cell(&mut self) -> Option<Point> {
+        self.free_cells.free.choose(&mut self.rng).copied()
+    }
+
+    /// Puts new food on a free cell. If there isn't one, the snake has filled the board,
+    /// which wins the game.
+    fn place_food(&mut self) {
+        self.food = self.pick_free_cell();
+        match self.food {
+            Some(food) => self.graphics.buf.set(food, Cell::Colored(FOOD_COLOR)),
+            None => self.win(),
+        }
+    }
+
+    fn win(&mut self) {
+        self.won = true;
+        for &point in &self.snake {
+            self.graphics
+                .buf
+                .set(point, Cell::Colored(WINNING_SNAKE_COLOR));
+        }
+        self.update_side_panel();
+    }
+
+    fn die(&mut self) {
+        self.alive = false;
+        self.update_side_panel();
     }
 }
 
 impl App for Snake {
     fn run_frame(&mut self) {
-        if !self.alive {
+        if !self.alive || self.won {
             return;
         }
 
-        let head = *self.snake.last().unwrap();
-        self.graphics.buf.set(head, Cell::Colored(SNAKE_COLOR));
-        let new_head = translated(head, self.direction);
-        if self.is_within_game_bounds(new_head) {
-            if new_head == self.food {
-                self.food = self.pick_new_food_location();
-                self.graphics.buf.set(self.food, Cell::Colored(FOOD_COLOR));
-                self.score += 1;
-                self.graphics.side_panel.as_mut().unwrap().items[0] = PanelItem::TextItem {
-                    text: format!("Score: {}", self.score),
-                };
-            } else {
-                self.graphics.buf.set(self.snake[0], Cell::Blank);
-                self.snake.remove(0);
-            }
+        self.take_turn();
+        let head = *self.snake.back().unwrap();
+        self.elapsed_millis += 1000 / self.speed;
+        let Some(new_head) = self.next_head(head) else {
+            self.die();
+            return;
+        };
+        let is_eating = Some(new_head) == self.food;
+        // Unless the snake grows, its tail moves out of the way, so the head can take its place
+        let tail = *self.snake.front().unwrap();
+        let is_moving_into_tail = !is_eating && new_head == tail;
+        if !self.free_cells.is_free(new_head) && !is_moving_into_tail {
+            self.die();
+            return;
+        }
+        if !is_eating {
+            self.snake.pop_front();
+            self.free_cells.release(tail);
+            self.graphics.buf.set(tail, Cell::Blank);
+        }
+        self.snake.push_back(new_head);
+        self.free_cells.take(new_head);
+        self.graphics.buf.set(new_head, Cell::Colored(SNAKE_COLOR));
 
-            if self.snake.contains(&new_head) {
-                self.alive = false;
+        if is_eating {
+            self.score += 1;
+            self.level_score += 1;
+            if let Some(max_speed) = self.max_speed {
+                if self.score.is_multiple_of(FOOD_PER_SPEED_UP) {
+                    self.speed = (self.speed + 1).min(max_speed);
+                }
+            }
+            let goal_reached = self
+                .level()
+                .goal
+                .is_some_and(|goal| self.level_score >= goal);
+            if goal_reached && self.level_index + 1 < self.levels.len() {
+                self.start_level(self.level_index + 1);
+            } else if goal_reached {
+                self.win();
             } else {
-                self.snake.push(new_head);
-                self.graphics.buf.set(new_head, Cell::Colored(SNAKE_COLOR));
+                self.place_food();
+                self.update_side_panel();
             }
-        } else {
-            self.alive = false;
-        }
-
-        if !self.alive {
-            self.graphics.side_panel.as_mut().unwrap().items[0] = PanelItem::TextItem {
-                text: format!("Game over.\nScore: {:?}", self.score),
-            };
         }
     }
 
-    fn handle_pressed_key(&mut self, key: char) {
-        let direction = match key {
-            'w' => Some(Direction::Up),
-            'a' => Some(Direction::Left),
-            's' => Some(Direction::Down),
-            'd' => Some(Direction::Right),
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if !event.is_press() {
+            return;
+        }
+        let direction = match event.code {
+            KeyCode::Char('w') | KeyCode::Up => Some(Direction::Up),
+            KeyCode::Char('a') | KeyCode::Left => Some(Direction::Left),
+            KeyCode::Char('s') | KeyCode::Down => Some(Direction::Down),
+            KeyCode::Char('d') | KeyCode::Right => Some(Direction::Right),
             _ => None,
         };
         if let Some(direction) = direction {
-            self.set_direction(direction);
+            self.queue_turn(direction);
         }
     }
 
     fn graphics(&self) -> &Graphics {
         &self.graphics
     }
+
+    fn frame_rate(&self) -> Option<u32> {
+        Some(self.speed)
+    }
+
+    fn status(&self) -> AppStatus {
+        if self.won {
+            AppStatus::Won { score: self.score }
+        } else if self.alive {
+            AppStatus::Running
+        } else {
+            AppStatus::GameOver { score: self.score }
+        }
+    }
+}
+
+impl Level {
+    /// A board without any walls, where the snake starts out going right
+    fn empty(dimensions: (u8, u8), wrap: bool) -> Self {
+        Self {
+            dimensions,
+            tiles: vec![Tile::Open; dimensions.0 as usize * dimensions.1 as usize],
+            wrap,
+            start: (1, min(5, dimensions.1 as i16 / 2)),
+            start_direction: Direction::Right,
+            goal: None,
+        }
+    }
+
+    fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Couldn't open {}: {}", path, e))?;
+        text.parse()
+    }
+
+    /// What is at the point, which has to be on the board
+    fn tile(&self, point: Point) -> Tile {
+        self.tiles[point.1 as usize * self.dimensions.0 as usize + point.0 as usize]
+    }
+
+    fn tile_mut(&mut self, point: Point) -> &mut Tile {
+        &mut self.tiles[point.1 as usize * self.dimensions.0 as usize + point.0 as usize]
+    }
+
+    /// The point itself if it's on the board. Otherwise, if the level wraps around, the
+    /// point on the opposite side.
+    fn wrapped(&self, point: Point) -> Option<Point> {
+        let (width, height) = (self.dimensions.0 as i16, self.dimensions.1 as i16);
+        let is_inside = (0..width).contains(&point.0) && (0..height).contains(&point.1);
+        if is_inside {
+            Some(point)
+        } else if self.wrap {
+            Some((point.0.rem_euclid(width), point.1.rem_euclid(height)))
+        } else {
+            None
+        }
+    }
+
+    /// Where going into a portal at the point comes out
+    fn portal_exit(&self, point: Point) -> Option<Point> {
+        match self.tile(point) {
+            Tile::Portal(exit) => Some(exit),
+            Tile::Open | Tile::Wall => None,
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().peekable();
+
+        let mut wrap = false;
+        let mut goal = None;
+        while let Some(line) = lines.peek() {
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["wrap"] => wrap = true,
+                ["goal", food] => {
+                    let food = food
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid goal: {:?}", food))?;
+                    goal = Some(food);
+                }
+                _ => break,
+            }
+            lines.next();
+        }
+
+        let mut walls = vec![];
+        let mut portal_ends: Vec<(char, Vec<Point>)> = vec![];
+        let mut start = None;
+        let (mut width, mut height) = (0, 0);
+        for (y, line) in lines.enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                let point = (x as i16, y as i16);
+                let direction = match ch {
+                    '^' => Direction::Up,
+                    '<' => Direction::Left,
+                    'v' => Direction::Down,
+                    '>' => Direction::Right,
+                    'x' => {
+                        walls.push(point);
+                        continue;
+                    }
+                    '.' | ' ' => continue,
+                    '0'..='9' => {
+                        match portal_ends.iter_mut().find(|(c, _)| *c == ch) {
+                            Some((_, ends)) => ends.push(point),
+                            None => portal_ends.push((ch, vec![point])),
+                        }
+                        continue;
+                    }
+                    _ => return Err(format!("Unexpected {:?} in the level", ch)),
+                };
+                if start.replace((point, direction)).is_some() {
+                    return Err("There can only be one start ('>', '<', '^' or 'v')".to_string());
+                }
+            }
+            width = width.max(line.chars().count());
+            height = y + 1;
+        }
+
+        let (start, start_direction) =
+            start.ok_or_else(|| "Must specify the start ('>', '<', '^' or 'v')".to_string())?;
+        if width < 3 || height < 3 {
+            return Err("The level is too small".to_string());
+        }
+        if width > u8::MAX as usize || height > u8::MAX as usize {
+            return Err("The level is too large".to_string());
+        }
+
+        let mut level = Self {
+            dimensions: (width as u8, height as u8),
+            tiles: vec![Tile::Open; width * height],
+            wrap,
+            start,
+            start_direction,
+            goal,
+        };
+        for wall in walls {
+            *level.tile_mut(wall) = Tile::Wall;
+        }
+        for (ch, ends) in portal_ends {
+            let [a, b] = ends[..] else {
+                return Err(format!("Portal {:?} must appear exactly twice", ch));
+            };
+            *level.tile_mut(a) = Tile::Portal(b);
+            *level.tile_mut(b) = Tile::Portal(a);
+        }
+        Ok(level)
+    }
+}
+
+impl FreeCells {
+    /// All of the level's open tiles
+    fn new(level: &Level) -> Self {
+        let (width, height) = (level.dimensions.0 as usize, level.dimensions.1 as usize);
+        let mut free = vec![];
+        let mut indices = vec![None; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let point = (x as i16, y as i16);
+                if level.tile(point) == Tile::Open {
+                    indices[y * width + x] = Some(free.len());
+                    free.push(point);
+                }
+            }
+        }
+        Self {
+            width,
+            free,
+            indices,
+        }
+    }
+
+    fn index(&self, point: Point) -> usize {
+        point.1 as usize * self.width + point.0 as usize
+    }
+
+    fn is_free(&self, point: Point) -> bool {
+        self.indices[self.index(point)].is_some()
+    }
+
+    fn take(&mut self, point: Point) {
+        let index = self.index(point);
+        let i = self.indices[index].take().unwrap();
+        self.free.swap_remove(i);
+        // The last free cell was moved into the gap
+        if let Some(&moved) = self.free.get(i) {
+            let moved_index = self.index(moved);
+            self.indices[moved_index] = Some(i);
+        }
+    }
+
+    fn release(&mut self, point: Point) {
+        let index = self.index(point);
+        self.indices[index] = Some(self.free.len());
+        self.free.push(point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::KeyEventKind;
+    use rand::SeedableRng;
+
+    fn new_snake(level: Level) -> Snake {
+        let options = SnakeOptions {
+            width: 30,
+            height: 20,
+            wrap: false,
+            levels: vec![level],
+            difficulty: Difficulty::Normal,
+            speed: None,
+            speed_up: false,
+        };
+        let ctx = AppContext {
+            rng: StdRng::seed_from_u64(0),
+        };
+        Snake::new(ctx, &options)
+    }
+
+    fn press(snake: &mut Snake, code: KeyCode) {
+        snake.handle_key_event(KeyEvent::new(code, Default::default(), KeyEventKind::Press));
+    }
+
+    #[test]
+    fn quick_turns_are_taken_one_step_at_a_time() {
+        let mut snake = new_snake(Level::empty((30, 20), false));
+        // Give the snake a body behind its head, which starts out at (1, 5) going right
+        snake.snake.push_front((0, 5));
+        snake.free_cells.take((0, 5));
+
+        // Turning back the way it came is ignored
+        press(&mut snake, KeyCode::Left);
+        snake.run_frame();
+        assert_eq!(snake.snake.back(), Some(&(2, 5)));
+
+        // Up and then left within one step, which makes a U-turn over two steps
+        press(&mut snake, KeyCode::Up);
+        press(&mut snake, KeyCode::Left);
+        snake.run_frame();
+        assert_eq!(snake.snake.back(), Some(&(2, 4)));
+        snake.run_frame();
+        assert_eq!(snake.snake.back(), Some(&(1, 4)));
+
+        // Down right after up would double back on the turn that's still queued
+        press(&mut snake, KeyCode::Up);
+        press(&mut snake, KeyCode::Down);
+        snake.run_frame();
+        snake.run_frame();
+        assert_eq!(snake.snake.back(), Some(&(1, 2)));
+        assert_eq!(snake.status(), AppStatus::Running);
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        let level = "xxxx\nx>.x\nxxxx\n".parse().unwrap();
+        let mut snake = new_snake(level);
+        snake.run_frame();
+        assert_eq!(snake.status(), AppStatus::Won { score: snake.score });
+    }
+
+    #[test]
+    fn portals_lead_into_each_other() {
+        // Going into the first 1 comes out right in front of the second 2, which leads
+        // back to the left
+        let level = "xxxxxxxxx\nx>12..12x\nxxxxxxxxx\n".parse().unwrap();
+        let mut snake = new_snake(level);
+        snake.run_frame();
+        assert_eq!(snake.status(), AppStatus::Running);
+        assert_eq!(snake.snake.back(), Some(&(4, 1)));
+    }
+
+    #[test]
+    fn portals_in_a_loop_are_deadly() {
+        // Going right out of either end of one portal leads into the other one
+        let mut level = Level::empty((5, 3), false);
+        *level.tile_mut((1, 0)) = Tile::Portal((3, 0));
+        *level.tile_mut((3, 0)) = Tile::Portal((1, 0));
+        *level.tile_mut((4, 0)) = Tile::Portal((0, 0));
+        *level.tile_mut((0, 0)) = Tile::Portal((4, 0));
+        let snake = new_snake(level);
+        assert_eq!(snake.next_head((0, 0)), None);
+    }
 }
@@ -1,79 +1,391 @@
-use crate::apps::RunConfig;
-use crate::{
-    translated, App, Cell, Color, Direction, Graphics, GraphicsBuf, PanelItem, Point, SidePanel,
-};
+mod board;
+mod bot;
+mod controls;
+mod pieces;
+mod scoring;
+mod versus;
+
+use crate::apps::{AppContext, AppStatus};
+use crate::input::{KeyEvent, KeyEventKind};
+use crate::scores::{self, ScoreKind};
+use crate::{App, Cell, Color, Direction, Graphics, GraphicsBuf, PanelItem, Point, SidePanel};
+use board::Board;
+use bot::{Evaluator, Weights};
+use controls::{Action, Controls};
+use pieces::{PieceSet, Rotation, Shape, Tetromino};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use scoring::{Scoring, TSpin};
+use std::collections::VecDeque;
+use versus::Versus;
 
 pub struct Tetris {
     graphics: Graphics,
+    /// The falling tetromino and its ghost are drawn on top of the board into the graphics
+    board: Board,
     falling: Option<Tetromino>,
-    upcoming: Shape,
+    randomizer: Randomizer,
+    upcoming: VecDeque<Shape>,
+    held: Option<Shape>,
+    /// Only one hold is allowed per tetromino, or you could keep it up in the air forever
+    can_hold: bool,
     holding_down: bool,
+    auto_shift: Option<AutoShift>,
+    /// How many frames the falling tetromino has been resting on something
+    lock_frames: u32,
+    /// How many times moving or rotating has put off the lock, since the tetromino last
+    /// reached a new lowest row
+    lock_resets: u32,
+    lowest_row: i16,
+    /// Which of the rotation's kicks was used, if the last successful move was a rotation
+    last_rotation_kick: Option<usize>,
     frame: u32,
-    fall_delay: u32,
-    score: u32,
+    /// How many frames the current tetromino has been falling
+    falling_frames: u32,
+    scoring: Scoring,
+    mode: Mode,
+    /// Plays the game instead of the player, if turned on
+    autopilot: Option<Box<dyn Evaluator>>,
+    autopilot_delay: u32,
+    /// Whether the game ended by reaching the mode's goal, rather than by topping out
+    completed: bool,
+    controls: Controls,
+    /// Rows of garbage to send to the opponent in versus mode
+    outgoing_garbage: u32,
+    /// Rows of garbage sent by the opponent, along with the column of the gap in them. They're
+    /// added to the bottom of the board the next time a tetromino locks without clearing lines.
+    incoming_garbage: VecDeque<(u32, i16)>,
+    /// Complete rows that are flashing before they're removed. The next tetromino only comes
+    /// in once they're gone.
+    clearing: Option<LineClear>,
+    /// The blocks of the tetromino that locked last, which light up for a moment
+    lock_flash: Option<LockFlash>,
+    /// How many frames the board has been turning grey since the game was lost
+    top_out_frames: u32,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TetrisOptions {
+    /// How the order of the pieces is picked
+    #[arg(long, value_enum, default_value = "bag")]
+    randomizer: RandomizerKind,
+
+    /// Width of the board
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u8).range(4..=40))]
+    width: u8,
+
+    /// Height of the board
+    #[arg(long, default_value = "20", value_parser = clap::value_parser!(u8).range(4..=40))]
+    height: u8,
+
+    /// What to play with: "tetrominoes", "trominoes", "pentominoes", or the path of a file
+    /// that describes the pieces
+    #[arg(long, default_value = "tetrominoes", value_parser = pieces::load_piece_set)]
+    pieces: &'static PieceSet,
+
+    /// How many upcoming pieces to show
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u8).range(1..=5))]
+    preview: u8,
+
+    /// What to play for
+    #[arg(long, value_enum, default_value = "endless")]
+    mode: Mode,
+
+    /// Let a bot play the game
+    #[arg(long)]
+    autopilot: bool,
+
+    /// How many frames the bot waits before placing each tetromino
+    #[arg(long, default_value = "6", value_parser = clap::value_parser!(u32).range(1..))]
+    autopilot_delay: u32,
+
+    /// How the bot judges the boards it could leave behind: the weights of the aggregate
+    /// column height, cleared lines, holes and bumpiness, separated by commas
+    #[arg(
+        long,
+        default_value = "-0.51,0.76,-0.36,-0.18",
+        allow_hyphen_values = true
+    )]
+    weights: Weights,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Play for as long as you can, speeding up every 10 lines
+    Endless,
+    /// Clear 150 lines, speeding up every 10 lines
+    Marathon,
+    /// Clear 40 lines as fast as you can
+    Sprint,
+    /// Score as much as you can in two minutes
+    Ultra,
+    /// Two players on one keyboard. Clearing several lines at once sends garbage to the
+    /// other player, and the last one standing wins.
+    Versus,
+}
+
+const MARATHON_LINES: u32 = 150;
+const SPRINT_LINES: u32 = 40;
+const ULTRA_FRAMES: u32 = 2 * 60 * FRAME_RATE;
+
+// All timings are in frames
+const FRAME_RATE: u32 = 30;
+
+/// How long a tetromino can rest on something before it locks
+const LOCK_DELAY: u32 = 15;
+/// How many times moving or rotating a resting tetromino can restart its lock delay
+const MAX_LOCK_RESETS: u32 = 15;
+/// How long a left or right key has to be held before the tetromino starts moving by itself
+/// (delayed auto-shift)
+const AUTO_SHIFT_DELAY: u32 = 5;
+/// How often the tetromino moves by itself after that (auto-repeat rate)
+const AUTO_REPEAT_RATE: u32 = 2;
+
+/// How long complete rows flash and fade out before they're removed
+const LINE_CLEAR_FRAMES: u32 = 10;
+/// How long a tetromino lights up after locking
+const LOCK_FLASH_FRAMES: u32 = 4;
+
+#[derive(Debug, Clone)]
+struct LineClear {
+    /// From the top down
+    rows: Vec<i16>,
+    frames_left: u32,
+}
+
+#[derive(Debug, Clone)]
+struct LockFlash {
+    blocks: Vec<Point>,
+    frames_left: u32,
+}
+
+/// A left or right key that is being held down
+#[derive(Debug, Clone, Copy)]
+struct AutoShift {
+    direction: Direction,
+    frames_held: u32,
+}
+
+// Indices of the side panel items that change during the game
+const SCORE_ITEM: usize = 0;
+const UPCOMING_ITEM: usize = 2;
+const HELD_ITEM: usize = 4;
+
+impl TetrisOptions {
+    /// Checks the options that depend on each other
+    pub fn validate(&self) -> Result<(), clap::Error> {
+        self.pieces
+            .check_board_size(self.width as i16, self.height as i16)
+            .map_err(|e| {
+                clap::Error::raw(clap::error::ErrorKind::ValueValidation, format!("{}\n", e))
+            })
+    }
+}
+
+/// Constructs the game for the chosen mode
+pub fn new_game(ctx: AppContext, options: &TetrisOptions) -> Box<dyn App> {
+    match options.mode {
+        Mode::Versus => Box::new(Versus::new(ctx, options)),
+        _ => Box::new(Tetris::new(ctx, options)),
+    }
 }
 
 impl Tetris {
-    pub fn new() -> (Self, RunConfig) {
-        let mut buf = GraphicsBuf::new((10, 20));
-        let falling = Tetromino::at_top(generate_next());
-        for block in falling.blocks() {
-            buf.set(block, Cell::Colored(falling.color()));
-        }
-        let help_text = "\
-Controls:
---------
-A: move left
-D: move right
-W: rotate
-S: fall faster
-"
-        .to_string();
-        let score = 0;
-        let upcoming = generate_next();
-        let mut upcoming_buf = GraphicsBuf::new((4, 2));
-        Self::render_upcoming_buf(upcoming, &mut upcoming_buf);
+    pub fn new(ctx: AppContext, options: &TetrisOptions) -> Self {
+        Self::with_controls(ctx, options, Controls::Solo)
+    }
 
+    fn with_controls(ctx: AppContext, options: &TetrisOptions, controls: Controls) -> Self {
+        let mut randomizer = Randomizer::new(options.randomizer, options.pieces, ctx.rng);
+        let board = Board::new((options.width, options.height));
+        let falling = Tetromino::at_top(randomizer.next(), board.width());
+        let help_text = if options.autopilot {
+            "Autopilot is on".to_string()
+        } else {
+            controls.help_text().to_string()
+        };
+        let upcoming: VecDeque<Shape> = (0..options.preview).map(|_| randomizer.next()).collect();
+        // One empty row in between the pieces
+        let (hint_width, hint_height) = options.pieces.hint_size();
+        let upcoming_buf = GraphicsBuf::new((hint_width, options.preview * (hint_height + 1) - 1));
+
+        let title = match options.mode {
+            Mode::Endless => "Tetris".to_string(),
+            mode => format!("Tetris: {:?}", mode),
+        };
         let graphics = Graphics::new(
-            "Tetris".to_string(),
+            title,
             Some(SidePanel {
                 items: vec![
                     PanelItem::TextItem {
-                        text: format!("Score: {}", score),
+                        text: "".to_string(),
                     },
                     PanelItem::TextItem {
                         text: "Next:".to_string(),
                     },
                     PanelItem::GraphicsItem { buf: upcoming_buf },
+                    PanelItem::TextItem {
+                        text: "Hold:".to_string(),
+                    },
+                    PanelItem::GraphicsItem {
+                        buf: GraphicsBuf::new((hint_width, hint_height)),
+                    },
                     PanelItem::TextItem { text: help_text },
                 ],
             }),
-            buf,
+            board.cells().clone(),
         );
-        let frame_rate = 30;
-
-        (
-            Self {
-                graphics,
-                falling: Some(falling),
-                upcoming,
-                holding_down: false,
-                frame: 0,
-                fall_delay: 15,
-                score,
-            },
-            RunConfig { frame_rate },
-        )
+
+        let mut this = Self {
+            graphics,
+            board,
+            falling: Some(falling),
+            randomizer,
+            upcoming,
+            held: None,
+            can_hold: true,
+            holding_down: false,
+            auto_shift: None,
+            lock_frames: 0,
+            lock_resets: 0,
+            lowest_row: falling.origin.1,
+            last_rotation_kick: None,
+            frame: 0,
+            falling_frames: 0,
+            // The timed modes are played at a constant speed
+            scoring: Scoring::new(!matches!(options.mode, Mode::Sprint | Mode::Ultra)),
+            mode: options.mode,
+            autopilot: options
+                .autopilot
+                .then(|| Box::new(options.weights) as Box<dyn Evaluator>),
+            autopilot_delay: options.autopilot_delay,
+            completed: false,
+            controls,
+            outgoing_garbage: 0,
+            incoming_garbage: VecDeque::new(),
+            clearing: None,
+            lock_flash: None,
+            top_out_frames: 0,
+        };
+        this.render();
+        this
     }
 
-    fn render_upcoming_buf(shape: Shape, buf: &mut GraphicsBuf) {
-        {
-            for i in 0..buf.dimensions().0 * buf.dimensions().1 {
-                buf.set_by_index(i as usize, Cell::Blank);
+    /// Draws the board, with the ghost, the falling tetromino and any animations on top, and
+    /// the side panel
+    fn render(&mut self) {
+        self.render_side_panel();
+        let buf = &mut self.graphics.buf;
+        buf.clone_from(self.board.cells());
+        if let Some(falling) = self.falling {
+            let ghost = self.board.dropped(falling);
+            for block in ghost.blocks() {
+                buf.set(block, Cell::Colored(dimmed(ghost.color())));
+            }
+            for block in falling.blocks() {
+                buf.set(block, Cell::Colored(falling.color()));
+            }
+        }
+
+        if let Some(flash) = &self.lock_flash {
+            for &block in &flash.blocks {
+                if let Some(Cell::Colored(color)) = buf.get(block) {
+                    buf.set(block, Cell::Colored(brightened(color)));
+                }
+            }
+        }
+        if let Some(clearing) = &self.clearing {
+            // The rows stay white for the first half, then fade out
+            let brightness =
+                (clearing.frames_left * 2).min(LINE_CLEAR_FRAMES) * 255 / LINE_CLEAR_FRAMES;
+            let color = (brightness as u8, brightness as u8, brightness as u8);
+            for &y in &clearing.rows {
+                for x in 0..self.board.width() {
+                    buf.set((x, y), Cell::Colored(color));
+                }
             }
+        }
+        // After topping out, the blocks turn grey one row at a time, from the bottom up
+        let height = self.board.height();
+        for y in height - self.top_out_frames as i16..height {
+            for x in 0..self.board.width() {
+                if let Some(Cell::Colored(color)) = buf.get((x, y)) {
+                    buf.set((x, y), Cell::Colored(greyed(color)));
+                }
+            }
+        }
+    }
+
+    /// Whether the game has ended, either by topping out or by reaching the mode's goal
+    fn is_over(&self) -> bool {
+        self.falling.is_none() && self.clearing.is_none()
+    }
+
+    fn render_side_panel(&mut self) {
+        let is_over = self.is_over();
+        let side_panel = self.graphics.side_panel_mut().unwrap();
+        let scoring = &self.scoring;
+        let mut text = match self.mode {
+            Mode::Endless | Mode::Versus => format!(
+                "Score: {}\nLevel: {}\nLines: {}",
+                scoring.score, scoring.level, scoring.lines
+            ),
+            Mode::Marathon => format!(
+                "Score: {}\nLevel: {}\nLines: {}/{}",
+                scoring.score, scoring.level, scoring.lines, MARATHON_LINES
+            ),
+            Mode::Sprint => format!(
+                "Time: {}\nLines: {}/{}",
+                scores::format_time(frames_to_millis(self.frame)),
+                scoring.lines,
+                SPRINT_LINES
+            ),
+            Mode::Ultra => format!(
+                "Score: {}\nLines: {}\nTime left: {}",
+                scoring.score,
+                scoring.lines,
+                scores::format_time(frames_to_millis(ULTRA_FRAMES - self.frame))
+            ),
+        };
+        if is_over {
+            let result = match (self.mode, self.completed) {
+                (Mode::Sprint, true) => "Finished!",
+                (Mode::Marathon, true) => "Marathon complete!",
+                (Mode::Ultra, true) => "Time's up!",
+                _ => "Topped out",
+            };
+            text.push_str(&format!("\n\n{}", result));
+        } else if let Some(last_clear) = &scoring.last_clear {
+            text.push_str(&format!("\n\n{}", last_clear));
+        }
+        *side_panel.unwrap_text_item_mut(SCORE_ITEM) = text;
+        let hint_size = self.randomizer.pieces.hint_size();
+        Self::render_shapes(
+            self.upcoming.iter().copied(),
+            hint_size,
+            side_panel.unwrap_graphics_item_mut(UPCOMING_ITEM),
+        );
+        Self::render_shapes(
+            self.held,
+            hint_size,
+            side_panel.unwrap_graphics_item_mut(HELD_ITEM),
+        );
+    }
+
+    /// Draws the shapes below each other, with one empty row in between
+    fn render_shapes(
+        shapes: impl IntoIterator<Item = Shape>,
+        hint_size: (u8, u8),
+        buf: &mut GraphicsBuf,
+    ) {
+        for i in 0..buf.dimensions().0 as usize * buf.dimensions().1 as usize {
+            buf.set_by_index(i, Cell::Blank);
+        }
 
-            let tetromino = Tetromino::in_upcoming_hint(shape);
+        let (hint_width, hint_height) = hint_size;
+        for (i, shape) in shapes.into_iter().enumerate() {
+            let tetromino = Tetromino::in_upcoming_hint(shape, hint_width)
+                .offset((0, i as i16 * (hint_height as i16 + 1)));
             for point in tetromino.blocks() {
                 buf.set(point, Cell::Colored(tetromino.color()));
             }
@@ -83,344 +395,509 @@ S: fall faster
 
 impl App for Tetris {
     fn run_frame(&mut self) {
-        if self.falling.is_none() {
-            // Game ended in a previous frame
+        if self.is_over() {
+            // Game ended in a previous frame, but the top-out animation might still be running
+            if !self.completed && self.top_out_frames < self.board.height() as u32 {
+                self.top_out_frames += 1;
+                self.render();
+            }
             return;
         }
 
         self.frame += 1;
-
-        if !self.holding_down && self.frame % self.fall_delay != 0 {
-            // Simulate slower fall speed by ignoring some frames
+        if self.mode == Mode::Ultra && self.frame >= ULTRA_FRAMES {
+            self.complete();
+            self.render();
             return;
         }
 
-        if !self.try_move(Direction::Down) {
-            // The falling tetromino just landed
-
-            self.remove_any_complete_rows();
-
-            self.falling = None;
-            let next = Tetromino::at_top(self.upcoming);
-            self.upcoming = generate_next();
-            match &mut self.graphics.side_panel.as_mut().unwrap().items[2] {
-                PanelItem::GraphicsItem { buf } => {
-                    Self::render_upcoming_buf(self.upcoming, buf);
-                }
-                unexpected => panic!("Unexpected panel item: {:?}", unexpected),
+        if let Some(flash) = &mut self.lock_flash {
+            flash.frames_left -= 1;
+            if flash.frames_left == 0 {
+                self.lock_flash = None;
             }
+        }
+        if self.clearing.is_some() {
+            self.run_line_clear();
+            self.render();
+            return;
+        }
 
-            let game_over = self.would_collide(next);
-
-            for block in next.blocks() {
-                self.graphics.buf.set(block, Cell::Colored(next.color()));
+        self.falling_frames += 1;
+        if self.autopilot.is_some() {
+            if self.falling_frames >= self.autopilot_delay {
+                self.run_autopilot();
             }
+        } else {
+            self.run_auto_shift();
+        }
+        if self.falling.is_none() {
+            self.render();
+            return;
+        }
 
-            if game_over {
-                self.graphics.side_panel.as_mut().unwrap().items[0] = PanelItem::TextItem {
-                    text: format!("Game over.\nScore: {:?}", self.score),
-                };
-
-                return;
+        // Simulate slower fall speed by only falling on some frames
+        if self.holding_down {
+            if self.try_move(Direction::Down) {
+                self.scoring.add_drop(1, false);
             }
+        } else if self.frame.is_multiple_of(self.scoring.fall_delay()) {
+            self.try_move(Direction::Down);
+        }
 
-            self.falling = Some(next);
+        if self.is_resting() {
+            self.lock_frames += 1;
+            if self.lock_frames >= LOCK_DELAY {
+                self.lock();
+            }
+        } else {
+            self.lock_frames = 0;
         }
+        self.render();
     }
 
-    fn handle_pressed_key(&mut self, key: char) {
-        if self.falling.is_none() {
-            // Game over
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if self.is_over() || self.autopilot.is_some() {
+            // Game over, or the bot is playing
             return;
         }
-        // TODO handle hold down movement
-        match key {
-            'a' => {
-                self.try_move(Direction::Left);
-            }
-            'd' => {
-                self.try_move(Direction::Right);
-            }
-            'w' => {
-                self.rotate_if_possible();
-            }
-            's' => {
-                let was_already = self.holding_down;
-                self.holding_down = true;
-                if !was_already {
-                    self.run_frame();
+        if self.falling.is_none() && !event.is_release() {
+            // Rows are being cleared, and there's nothing to move yet
+            return;
+        }
+        let Some(action) = self.controls.action(event.code) else {
+            return;
+        };
+        match action {
+            Action::MoveLeft => self.handle_shift_key(Direction::Left, event.kind),
+            Action::MoveRight => self.handle_shift_key(Direction::Right, event.kind),
+            Action::SoftDrop => match event.kind {
+                KeyEventKind::Press => {
+                    self.holding_down = true;
+                    if self.try_move(Direction::Down) {
+                        self.scoring.add_drop(1, false);
+                    }
                 }
+                // While the key is held, the tetromino falls by one row every frame
+                KeyEventKind::Repeat => {}
+                KeyEventKind::Release => self.holding_down = false,
+            },
+            _ if event.is_release() => {}
+            Action::RotateClockwise => {
+                self.try_rotate(Rotation::Clockwise);
+            }
+            Action::RotateCounterClockwise => {
+                self.try_rotate(Rotation::CounterClockwise);
             }
-            _ => {}
+            Action::HardDrop if event.kind == KeyEventKind::Press => self.hard_drop(),
+            Action::Hold if event.kind == KeyEventKind::Press => self.hold(),
+            Action::HardDrop | Action::Hold => {}
         };
+        self.render();
     }
 
-    fn handle_released_key(&mut self, key: char) {
-        if key == 's' {
-            self.holding_down = false;
+    fn graphics(&self) -> &Graphics {
+        &self.graphics
+    }
+
+    fn status(&self) -> AppStatus {
+        if !self.is_over() {
+            return AppStatus::Running;
+        }
+        let score = match self.mode {
+            Mode::Sprint if self.completed => frames_to_millis(self.frame),
+            Mode::Sprint => 0,
+            _ => self.scoring.score,
+        };
+        if self.completed {
+            AppStatus::Won { score }
+        } else {
+            AppStatus::GameOver { score }
         }
     }
 
-    fn graphics(&self) -> &Graphics {
-        &self.graphics
+    fn score_kind(&self) -> ScoreKind {
+        match self.mode {
+            Mode::Sprint => ScoreKind::Time,
+            _ => ScoreKind::Points,
+        }
     }
 }
 
 impl Tetris {
-    fn try_move(&mut self, direction: Direction) -> bool {
-        let moved = self.falling.unwrap().translate(direction);
-
-        if self.would_collide(moved) {
-            false
-        } else {
-            for block in self.falling.unwrap().blocks() {
-                self.graphics.buf.set(block, Cell::Blank);
+    /// Moves once right away, and keeps moving if the key is held for long enough.
+    ///
+    /// Runtimes that can't tell when a key is released send a quick press and release
+    /// instead, once for each time the key repeats, which moves once for each of them.
+    fn handle_shift_key(&mut self, direction: Direction, kind: KeyEventKind) {
+        match kind {
+            KeyEventKind::Press => {
+                self.try_move(direction);
+                self.auto_shift = Some(AutoShift {
+                    direction,
+                    frames_held: 0,
+                });
             }
-            for moved_block in moved.blocks() {
-                self.graphics
-                    .buf
-                    .set(moved_block, Cell::Colored(moved.color()));
+            // Held keys are repeated by the auto-shift instead, at its own pace
+            KeyEventKind::Repeat => {}
+            KeyEventKind::Release => {
+                if self
+                    .auto_shift
+                    .is_some_and(|shift| shift.direction == direction)
+                {
+                    self.auto_shift = None;
+                }
             }
-            self.falling = Some(moved);
-            true
         }
     }
 
-    fn rotate_if_possible(&mut self) {
-        let rotated = self.falling.unwrap().rotated();
+    fn run_auto_shift(&mut self) {
+        let Some(shift) = &mut self.auto_shift else {
+            return;
+        };
+        shift.frames_held += 1;
+        let AutoShift {
+            direction,
+            frames_held,
+        } = *shift;
+        if frames_held >= AUTO_SHIFT_DELAY
+            && (frames_held - AUTO_SHIFT_DELAY).is_multiple_of(AUTO_REPEAT_RATE)
+        {
+            self.try_move(direction);
+        }
+    }
 
-        if !self.would_collide(rotated) {
-            for block in self.falling.unwrap().blocks() {
-                self.graphics.buf.set(block, Cell::Blank);
-            }
-            for rotated_block in rotated.blocks() {
-                self.graphics
-                    .buf
-                    .set(rotated_block, Cell::Colored(rotated.color()));
-            }
-            self.falling = Some(rotated);
+    /// Places the falling tetromino where the bot thinks is best
+    fn run_autopilot(&mut self) {
+        let evaluator = self.autopilot.as_deref().unwrap();
+        let alternative = match self.held {
+            _ if !self.can_hold => None,
+            Some(held) => Some(held),
+            None => self.upcoming.front().copied(),
+        };
+        let current = self.falling.unwrap();
+        let Some(placement) = bot::best_placement(&self.board, current, alternative, evaluator)
+        else {
+            return;
+        };
+
+        if placement.hold {
+            self.hold();
+        }
+        for _ in 0..placement.rotations {
+            self.try_rotate(Rotation::Clockwise);
+        }
+        let direction = if placement.shift < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        for _ in 0..placement.shift.abs() {
+            self.try_move(direction);
         }
+        self.hard_drop();
     }
 
-    fn remove_any_complete_rows(&mut self) {
-        let mut y = self.graphics.buf.dimensions().1 as i16 - 1;
-        while y >= 0 {
-            let mut is_complete_row = true;
-            for x in 0..self.graphics.buf.dimensions.0 {
-                if self.graphics.buf.get((x as i16, y as i16)).unwrap() == Cell::Blank {
-                    is_complete_row = false;
-                    break;
-                }
-            }
-            if is_complete_row {
-                self.score += 1;
-                self.graphics.side_panel.as_mut().unwrap().items[0] = PanelItem::TextItem {
-                    text: format!("Score: {:?}", self.score),
-                };
-                if self.score % 2 == 0 {
-                    self.fall_delay = std::cmp::max(1, self.fall_delay - 1);
-                }
-                for shift_y in (0..y + 1).rev() {
-                    let shift_y = shift_y as i16;
-                    for x in 0..self.graphics.buf.dimensions.0 {
-                        let x = x as i16;
-                        let value_above = self
-                            .graphics
-                            .buf
-                            .get((x, shift_y - 1))
-                            .unwrap_or(Cell::Blank);
-                        self.graphics.buf.set((x, shift_y), value_above);
-                    }
-                }
-            } else {
-                y -= 1;
+    fn try_move(&mut self, direction: Direction) -> bool {
+        let Some(moved) = self.board.moved(self.falling.unwrap(), direction) else {
+            return false;
+        };
+        self.falling = Some(moved);
+        self.last_rotation_kick = None;
+        if let Direction::Down = direction {
+            if moved.origin.1 > self.lowest_row {
+                self.lowest_row = moved.origin.1;
+                self.lock_resets = 0;
             }
+        } else {
+            self.put_off_lock();
         }
+        true
     }
 
-    fn would_collide(&self, hypothetical: Tetromino) -> bool {
-        hypothetical.blocks().iter().any(|block| {
-            // we ignore "self collision"
-            let collision_with_falling = self
-                .falling
-                .map(|tetromino| tetromino.blocks().contains(block))
-                .unwrap_or(false);
-            let collision = self
-                .graphics
-                .buf
-                .get(*block)
-                .map(|cell| cell != Cell::Blank)
-                .unwrap_or(true);
-            collision && !collision_with_falling
-        })
+    fn is_resting(&self) -> bool {
+        self.board
+            .moved(self.falling.unwrap(), Direction::Down)
+            .is_none()
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-struct Tetromino {
-    origin: Point,
-    orientation: Orientation,
-    shape: Shape,
-}
+    /// Restarts the lock delay after the falling tetromino was moved or rotated, unless
+    /// that has happened too many times already
+    fn put_off_lock(&mut self) {
+        if self.lock_frames > 0 && self.lock_resets < MAX_LOCK_RESETS {
+            self.lock_frames = 0;
+            self.lock_resets += 1;
+        }
+    }
 
-#[derive(Debug, Clone, Copy)]
-enum Shape {
-    I,
-    O,
-    T,
-    S,
-    Z,
-    J,
-    L,
-}
+    fn try_rotate(&mut self, rotation: Rotation) -> bool {
+        let Some((rotated, kick)) = self.board.rotated(self.falling.unwrap(), rotation) else {
+            return false;
+        };
+        self.falling = Some(rotated);
+        self.last_rotation_kick = Some(kick);
+        self.put_off_lock();
+        true
+    }
 
-impl Tetromino {
-    fn at_top(shape: Shape) -> Self {
-        let orientation = Orientation::First;
-        let origin = match shape {
-            Shape::I => (3, -2),
-            Shape::O => (4, 0),
-            Shape::T => (4, -1),
-            Shape::S => (4, -1),
-            Shape::Z => (4, -1),
-            Shape::J => (4, -1),
-            Shape::L => (4, -1),
+    /// Drops the falling tetromino straight down, and locks it right away
+    fn hard_drop(&mut self) {
+        let falling = self.falling.unwrap();
+        let dropped = self.board.dropped(falling);
+        let rows = (dropped.origin.1 - falling.origin.1) as u32;
+        if rows > 0 {
+            self.falling = Some(dropped);
+            self.last_rotation_kick = None;
+            self.scoring.add_drop(rows, true);
+        }
+        self.lock();
+    }
+
+    /// Puts the falling tetromino away for later, and brings back the one that was held
+    /// before, if any
+    fn hold(&mut self) {
+        if !self.can_hold {
+            return;
+        }
+        let falling = self.falling.unwrap();
+        let next = match self.held.replace(falling.shape) {
+            Some(held) => held,
+            None => self.next_from_queue(),
         };
-        Self {
-            origin,
-            orientation,
-            shape,
-        }
-    }
-
-    fn in_upcoming_hint(shape: Shape) -> Self {
-        let orientation = Orientation::First;
-        let origin = match shape {
-            Shape::I => (0, -2),
-            Shape::O => (0, 0),
-            Shape::T => (0, -1),
-            Shape::S => (0, -1),
-            Shape::Z => (0, -1),
-            Shape::J => (0, -1),
-            Shape::L => (0, -1),
+        self.spawn(next);
+        self.can_hold = false;
+    }
+
+    /// Adds the falling tetromino to the board. The next one comes in right away, unless
+    /// rows were completed, in which case it waits until they've been cleared.
+    fn lock(&mut self) {
+        let falling = self.falling.take().unwrap();
+        self.board.place(falling);
+        self.lock_flash = Some(LockFlash {
+            blocks: falling.blocks().collect(),
+            frames_left: LOCK_FLASH_FRAMES,
+        });
+        let t_spin = self.t_spin(falling);
+        let rows = self.board.complete_rows();
+        let lines = rows.len() as u32;
+        self.scoring.add_lock(lines, t_spin);
+
+        // Sent garbage cancels out garbage that is on its way in, before it reaches the opponent
+        let mut sent = scoring::garbage_to_send(lines, t_spin);
+        while sent > 0 {
+            let Some((rows, _)) = self.incoming_garbage.front_mut() else {
+                break;
+            };
+            let cancelled = sent.min(*rows);
+            sent -= cancelled;
+            *rows -= cancelled;
+            if *rows == 0 {
+                self.incoming_garbage.pop_front();
+            }
+        }
+        self.outgoing_garbage += sent;
+        if lines == 0 {
+            for (rows, gap) in self.incoming_garbage.drain(..) {
+                self.board.add_garbage(rows, gap);
+            }
+        }
+
+        let goal = match self.mode {
+            Mode::Marathon => Some(MARATHON_LINES),
+            Mode::Sprint => Some(SPRINT_LINES),
+            Mode::Endless | Mode::Ultra | Mode::Versus => None,
         };
-        Self {
-            origin,
-            orientation,
-            shape,
+        if !rows.is_empty() {
+            self.clearing = Some(LineClear {
+                rows,
+                frames_left: LINE_CLEAR_FRAMES,
+            });
+        }
+        if goal.is_some_and(|goal| self.scoring.lines >= goal) {
+            self.complete();
+        } else if self.clearing.is_none() {
+            self.spawn_next();
         }
     }
 
-    fn blocks(&self) -> [Point; 4] {
-        match self.shape {
-            Shape::I => match self.orientation {
-                Orientation::First | Orientation::Third => {
-                    self.resolve([(0, 2), (1, 2), (2, 2), (3, 2)])
-                }
-                Orientation::Second | Orientation::Fourth => {
-                    self.resolve([(2, 0), (2, 1), (2, 2), (2, 3)])
-                }
-            },
-            Shape::O => self.resolve([(0, 0), (1, 0), (0, 1), (1, 1)]),
-            Shape::T => match self.orientation {
-                Orientation::First => self.resolve([(0, 1), (1, 1), (2, 1), (1, 2)]),
-                Orientation::Second => self.resolve([(0, 1), (1, 0), (1, 1), (1, 2)]),
-                Orientation::Third => self.resolve([(0, 1), (1, 1), (2, 1), (1, 0)]),
-                Orientation::Fourth => self.resolve([(2, 1), (1, 0), (1, 1), (1, 2)]),
-            },
-            Shape::S => match self.orientation {
-                Orientation::First | Orientation::Third => {
-                    self.resolve([(0, 2), (1, 2), (1, 1), (2, 1)])
-                }
-                Orientation::Second | Orientation::Fourth => {
-                    self.resolve([(1, 0), (1, 1), (2, 1), (2, 2)])
-                }
-            },
-            Shape::Z => match self.orientation {
-                Orientation::First | Orientation::Third => {
-                    self.resolve([(0, 1), (1, 1), (1, 2), (2, 2)])
-                }
-                Orientation::Second | Orientation::Fourth => {
-                    self.resolve([(1, 2), (1, 1), (2, 1), (2, 0)])
-                }
-            },
-            Shape::J => match self.orientation {
-                Orientation::First => self.resolve([(0, 1), (1, 1), (2, 1), (2, 2)]),
-                Orientation::Second => self.resolve([(0, 2), (1, 2), (1, 1), (1, 0)]),
-                Orientation::Third => self.resolve([(0, 0), (0, 1), (1, 1), (2, 1)]),
-                Orientation::Fourth => self.resolve([(1, 2), (1, 1), (1, 0), (2, 0)]),
-            },
-            Shape::L => match self.orientation {
-                Orientation::First => self.resolve([(0, 2), (0, 1), (1, 1), (2, 1)]),
-                Orientation::Second => self.resolve([(0, 0), (1, 0), (1, 1), (1, 2)]),
-                Orientation::Third => self.resolve([(0, 1), (1, 1), (2, 1), (2, 0)]),
-                Orientation::Fourth => self.resolve([(1, 0), (1, 1), (1, 2), (2, 2)]),
-            },
+    /// Counts down the flashing of the complete rows, then removes them and brings in the
+    /// next tetromino
+    fn run_line_clear(&mut self) {
+        let clearing = self.clearing.as_mut().unwrap();
+        clearing.frames_left -= 1;
+        if clearing.frames_left == 0 {
+            let rows = self.clearing.take().unwrap().rows;
+            self.board.remove_rows(&rows);
+            self.spawn_next();
+        }
+    }
+
+    /// Whether the tetromino is a T that was just rotated into a tight spot, judging by how
+    /// many of the corners around its center are filled
+    fn t_spin(&self, tetromino: Tetromino) -> Option<TSpin> {
+        let kick = self.last_rotation_kick?;
+        if !tetromino.shape.t_spins {
+            return None;
+        }
+        let is_filled = |point: Point| self.board.is_filled(point);
+        let (front, back) = tetromino.t_corners();
+        let front = front.into_iter().filter(|&point| is_filled(point)).count();
+        let back = back.into_iter().filter(|&point| is_filled(point)).count();
+        if front + back < 3 {
+            None
+        } else if front == 2 || kick == 4 {
+            // The last kick moves the T so far that it counts as a full T-spin anyway
+            Some(TSpin::Full)
+        } else {
+            Some(TSpin::Mini)
         }
     }
 
-    fn color(&self) -> Color {
-        match self.shape {
-            Shape::I => (235, 50, 50),
-            Shape::O => (50, 235, 50),
-            Shape::T => (80, 80, 235),
-            Shape::S => (170, 170, 50),
-            Shape::Z => (50, 170, 170),
-            Shape::J => (170, 50, 170),
-            Shape::L => (200, 100, 100),
+    /// Ends the game by reaching the mode's goal. Rows that are still being cleared are
+    /// removed right away.
+    fn complete(&mut self) {
+        if let Some(clearing) = self.clearing.take() {
+            self.board.remove_rows(&clearing.rows);
         }
+        self.falling = None;
+        self.completed = true;
     }
 
-    fn resolve(&self, points: [Point; 4]) -> [Point; 4] {
-        points.map(|r| (self.origin.0 + r.0, self.origin.1 + r.1))
+    fn spawn_next(&mut self) {
+        let next = self.next_from_queue();
+        self.spawn(next);
+        self.can_hold = true;
     }
 
-    fn translate(&self, direction: Direction) -> Self {
-        Self {
-            origin: translated(self.origin, direction),
-            orientation: self.orientation,
-            shape: self.shape,
-        }
+    fn next_from_queue(&mut self) -> Shape {
+        let next = self.upcoming.pop_front().unwrap();
+        self.upcoming.push_back(self.randomizer.next());
+        next
     }
 
-    fn rotated(&mut self) -> Self {
-        Self {
-            origin: self.origin,
-            orientation: self.orientation.rotated(),
-            shape: self.shape,
+    fn spawn(&mut self, shape: Shape) {
+        let next = Tetromino::at_top(shape, self.board.width());
+        if self.board.would_collide(next) {
+            // Game over. The tetromino is still shown, overlapping whatever it collided with.
+            self.board.place(next);
+            self.falling = None;
+        } else {
+            self.falling = Some(next);
+            self.lock_frames = 0;
+            self.lock_resets = 0;
+            self.lowest_row = next.origin.1;
+            self.last_rotation_kick = None;
+            self.falling_frames = 0;
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Orientation {
-    First,
-    Second,
-    Third,
-    Fourth,
+fn frames_to_millis(frames: u32) -> u32 {
+    frames * 1000 / FRAME_RATE
+}
+
+fn dimmed(color: Color) -> Color {
+    (color.0 / 3, color.1 / 3, color.2 / 3)
+}
+
+/// Halfway between the color and white
+fn brightened(color: Color) -> Color {
+    let brighten = |c: u8| c + (255 - c) / 2;
+    (brighten(color.0), brighten(color.1), brighten(color.2))
+}
+
+/// A grey that is as bright as the color
+fn greyed(color: Color) -> Color {
+    let grey = ((color.0 as u16 + color.1 as u16 + color.2 as u16) / 3) as u8;
+    (grey, grey, grey)
 }
 
-impl Orientation {
-    fn rotated(&self) -> Self {
-        match self {
-            Orientation::First => Orientation::Second,
-            Orientation::Second => Orientation::Third,
-            Orientation::Third => Orientation::Fourth,
-            Orientation::Fourth => Orientation::First,
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum RandomizerKind {
+    /// Deal the pieces from a shuffled bag of one of each, refilled when it runs out
+    Bag,
+    /// Pick every piece independently, at random
+    Uniform,
+}
+
+struct Randomizer {
+    kind: RandomizerKind,
+    pieces: &'static PieceSet,
+    bag: Vec<Shape>,
+    rng: StdRng,
+}
+
+impl Randomizer {
+    fn new(kind: RandomizerKind, pieces: &'static PieceSet, rng: StdRng) -> Self {
+        Self {
+            kind,
+            pieces,
+            bag: vec![],
+            rng,
+        }
+    }
+
+    fn next(&mut self) -> Shape {
+        match self.kind {
+            RandomizerKind::Bag => {
+                if self.bag.is_empty() {
+                    self.bag = self.pieces.shapes.iter().collect();
+                    self.bag.shuffle(&mut self.rng);
+                }
+                self.bag.pop().unwrap()
+            }
+            RandomizerKind::Uniform => self.pieces.shapes.choose(&mut self.rng).unwrap(),
         }
     }
 }
 
-fn generate_next() -> Shape {
-    let shapes = [
-        Shape::I,
-        Shape::O,
-        Shape::T,
-        Shape::S,
-        Shape::Z,
-        Shape::J,
-        Shape::L,
-    ];
-
-    *shapes.choose(&mut rand::thread_rng()).unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Args, Command, FromArgMatches};
+    use rand::SeedableRng;
+
+    fn new_tetris() -> Tetris {
+        let matches = TetrisOptions::augment_args(Command::new("tetris"))
+            .try_get_matches_from(["tetris"])
+            .unwrap();
+        let options = TetrisOptions::from_arg_matches(&matches).unwrap();
+        let ctx = AppContext {
+            rng: StdRng::seed_from_u64(0),
+        };
+        Tetris::new(ctx, &options)
+    }
+
+    #[test]
+    fn t_spins_depend_on_the_filled_corners() {
+        let mut tetris = new_tetris();
+        // The T and the O of the built-in set
+        let shapes = &tetris.randomizer.pieces.shapes;
+        // A T pointing up, resting on the floor, with its bounding box spanning x 3..=5
+        let t = Tetromino::at_top(&shapes[2], 10).offset((0, 17));
+        let o = Tetromino::at_top(&shapes[1], 10);
+        // An O with its top left corner at x, covering the row of the T's front corners
+        let o_at = |x: i16| o.offset((x - o.origin.0, 16 - o.origin.1));
+        tetris.last_rotation_kick = Some(0);
+
+        // Both corners behind the T, but none in front
+        tetris.board = Board::new((10, 20));
+        tetris.board.add_garbage(1, 4);
+        assert_eq!(tetris.t_spin(t), None);
+
+        // One corner in front
+        tetris.board.place(o_at(2));
+        assert_eq!(tetris.t_spin(t), Some(TSpin::Mini));
+        // The last kick makes it a full T-spin anyway
+        tetris.last_rotation_kick = Some(4);
+        assert_eq!(tetris.t_spin(t), Some(TSpin::Full));
+        // As do both corners in front
+        tetris.last_rotation_kick = Some(0);
+        tetris.board.place(o_at(5));
+        assert_eq!(tetris.t_spin(t), Some(TSpin::Full));
+
+        // Without a rotation, it's no T-spin at all
+        tetris.last_rotation_kick = None;
+        assert_eq!(tetris.t_spin(t), None);
+    }
 }
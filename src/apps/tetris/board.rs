@@ -0,0 +1,128 @@
+use super::pieces::{Rotation, Tetromino};
+use crate::{Cell, Color, Direction, GraphicsBuf, Point};
+
+const GARBAGE: Color = (120, 120, 120);
+
+/// The blocks that have landed. Tetrominoes only ever collide with these, so the board can
+/// be copied to try out moves without touching the game.
+#[derive(Debug, Clone)]
+pub struct Board {
+    cells: GraphicsBuf,
+}
+
+impl Board {
+    pub fn new(dimensions: (u8, u8)) -> Self {
+        Self {
+            cells: GraphicsBuf::new(dimensions),
+        }
+    }
+
+    pub fn cells(&self) -> &GraphicsBuf {
+        &self.cells
+    }
+
+    pub fn width(&self) -> i16 {
+        self.cells.dimensions().0 as i16
+    }
+
+    pub fn height(&self) -> i16 {
+        self.cells.dimensions().1 as i16
+    }
+
+    /// Anything outside of the board counts as filled
+    pub fn is_filled(&self, point: Point) -> bool {
+        self.cells
+            .get(point)
+            .map(|cell| cell != Cell::Blank)
+            .unwrap_or(true)
+    }
+
+    pub fn would_collide(&self, tetromino: Tetromino) -> bool {
+        tetromino.blocks().any(|block| self.is_filled(block))
+    }
+
+    pub fn moved(&self, tetromino: Tetromino, direction: Direction) -> Option<Tetromino> {
+        let moved = tetromino.translate(direction);
+        if self.would_collide(moved) {
+            None
+        } else {
+            Some(moved)
+        }
+    }
+
+    /// Rotates the tetromino, following the Super Rotation System: if the rotated tetromino
+    /// doesn't fit, it's nudged ("kicked") to each of a handful of nearby positions in turn,
+    /// and the first one that fits is used. Returns which of the kicks that was.
+    pub fn rotated(&self, tetromino: Tetromino, rotation: Rotation) -> Option<(Tetromino, usize)> {
+        let rotated = tetromino.rotated(rotation);
+        tetromino
+            .kicks(rotation)
+            .into_iter()
+            .map(|kick| rotated.offset(kick))
+            .enumerate()
+            .find(|&(_, kicked)| !self.would_collide(kicked))
+            .map(|(i, kicked)| (kicked, i))
+    }
+
+    /// Where the tetromino would land if it was dropped straight down
+    pub fn dropped(&self, tetromino: Tetromino) -> Tetromino {
+        let mut dropped = tetromino;
+        while let Some(moved) = self.moved(dropped, Direction::Down) {
+            dropped = moved;
+        }
+        dropped
+    }
+
+    pub fn place(&mut self, tetromino: Tetromino) {
+        for block in tetromino.blocks() {
+            self.cells.set(block, Cell::Colored(tetromino.color()));
+        }
+    }
+
+    /// The rows that are completely filled, from the top down
+    pub fn complete_rows(&self) -> Vec<i16> {
+        (0..self.height())
+            .filter(|&y| (0..self.width()).all(|x| self.is_filled((x, y))))
+            .collect()
+    }
+
+    /// Removes the rows, which have to be sorted from the top down, and moves everything
+    /// above them down to fill the gap
+    pub fn remove_rows(&mut self, rows: &[i16]) {
+        // Removing a row only moves the ones above it, so the rows that are still to be
+        // removed stay where they are
+        for &y in rows {
+            for shift_y in (0..y + 1).rev() {
+                for x in 0..self.width() {
+                    let value_above = self.cells.get((x, shift_y - 1)).unwrap_or(Cell::Blank);
+                    self.cells.set((x, shift_y), value_above);
+                }
+            }
+        }
+    }
+
+    /// Returns how many rows were removed
+    pub fn remove_complete_rows(&mut self) -> u32 {
+        let rows = self.complete_rows();
+        self.remove_rows(&rows);
+        rows.len() as u32
+    }
+
+    /// Pushes everything up, and fills the rows at the bottom with blocks, except for one
+    /// column. Anything that is pushed past the top is lost.
+    pub fn add_garbage(&mut self, rows: u32, gap: i16) {
+        let rows = rows as i16;
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let cell = if y + rows < self.height() {
+                    self.cells.get((x, y + rows)).unwrap()
+                } else if x == gap {
+                    Cell::Blank
+                } else {
+                    Cell::Colored(GARBAGE)
+                };
+                self.cells.set((x, y), cell);
+            }
+        }
+    }
+}
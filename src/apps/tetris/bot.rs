@@ -0,0 +1,196 @@
+use super::board::Board;
+use super::pieces::{Rotation, Shape, Tetromino};
+use crate::Direction;
+use std::str::FromStr;
+
+/// Judges how good a board is, after a tetromino was placed on it and any complete lines
+/// were cleared. Higher is better.
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board, lines_cleared: u32) -> f64;
+}
+
+/// Weighs a few simple features of the board against each other.
+///
+/// Parsed from four comma-separated numbers: the weights of the aggregate height, the
+/// cleared lines, the holes and the bumpiness, in that order.
+#[derive(Debug, Clone, Copy)]
+pub struct Weights {
+    /// The sum of all column heights
+    pub height: f64,
+    pub lines: f64,
+    /// Empty cells with a filled cell somewhere above them
+    pub holes: f64,
+    /// The sum of the height differences between neighbouring columns
+    pub bumpiness: f64,
+}
+
+impl FromStr for Weights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s
+            .split(',')
+            .map(|weight| {
+                weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid weight: {:?}", weight))
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        match weights[..] {
+            [height, lines, holes, bumpiness] => Ok(Self {
+                height,
+                lines,
+                holes,
+                bumpiness,
+            }),
+            _ => Err(format!("Expected 4 weights, got {}", weights.len())),
+        }
+    }
+}
+
+impl Evaluator for Weights {
+    fn evaluate(&self, board: &Board, lines_cleared: u32) -> f64 {
+        let heights = column_heights(board);
+        let height: i16 = heights.iter().sum();
+        let bumpiness: i16 = heights
+            .windows(2)
+            .map(|pair| (pair[0] - pair[1]).abs())
+            .sum();
+        self.height * height as f64
+            + self.lines * lines_cleared as f64
+            + self.holes * count_holes(board) as f64
+            + self.bumpiness * bumpiness as f64
+    }
+}
+
+fn column_heights(board: &Board) -> Vec<i16> {
+    (0..board.width())
+        .map(|x| {
+            let top = (0..board.height())
+                .find(|&y| board.is_filled((x, y)))
+                .unwrap_or(board.height());
+            board.height() - top
+        })
+        .collect()
+}
+
+fn count_holes(board: &Board) -> usize {
+    (0..board.width())
+        .map(|x| {
+            (0..board.height())
+                .skip_while(|&y| !board.is_filled((x, y)))
+                .filter(|&y| !board.is_filled((x, y)))
+                .count()
+        })
+        .sum()
+}
+
+/// Where to put a tetromino: whether to hold it and use the other one instead, how many
+/// times to rotate it clockwise from where it is (or from where the other one spawns), and
+/// how far to move it sideways before dropping it.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    pub hold: bool,
+    pub rotations: usize,
+    pub shift: i16,
+}
+
+/// Tries every placement of the current tetromino, starting from wherever it has fallen to,
+/// and of the one that holding would bring in (if any), and picks the one that leaves the
+/// best board behind.
+pub fn best_placement(
+    board: &Board,
+    current: Tetromino,
+    alternative: Option<Shape>,
+    evaluator: &dyn Evaluator,
+) -> Option<Placement> {
+    let mut best: Option<(Placement, f64)> = None;
+    let alternative = alternative.map(|shape| Tetromino::at_top(shape, board.width()));
+    let candidates = [(false, Some(current)), (true, alternative)];
+    for (hold, start) in candidates {
+        let Some(start) = start else {
+            continue;
+        };
+        for rotations in 0..4 {
+            let Some(rotated) = rotate(board, start, rotations) else {
+                continue;
+            };
+            for direction in [Direction::Left, Direction::Right] {
+                let step = if direction == Direction::Left { -1 } else { 1 };
+                let mut moved = Some(rotated);
+                let mut shift = 0;
+                while let Some(tetromino) = moved {
+                    // Not moving at all is only tried once, along with the moves to the left
+                    if shift != 0 || direction == Direction::Left {
+                        let mut result = board.clone();
+                        result.place(board.dropped(tetromino));
+                        let lines = result.remove_complete_rows();
+                        let score = evaluator.evaluate(&result, lines);
+                        if best.is_none_or(|(_, best_score)| score > best_score) {
+                            let placement = Placement {
+                                hold,
+                                rotations,
+                                shift,
+                            };
+                            best = Some((placement, score));
+                        }
+                    }
+                    moved = board.moved(tetromino, direction);
+                    shift += step;
+                }
+            }
+        }
+    }
+    best.map(|(placement, _)| placement)
+}
+
+fn rotate(board: &Board, tetromino: Tetromino, rotations: usize) -> Option<Tetromino> {
+    if board.would_collide(tetromino) {
+        return None;
+    }
+    let mut rotated = tetromino;
+    for _ in 0..rotations {
+        rotated = board.rotated(rotated, Rotation::Clockwise)?.0;
+    }
+    Some(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::tetris::pieces;
+
+    #[test]
+    fn placements_start_from_where_the_tetromino_has_fallen() {
+        // An overhang across most of the board, with an O that has already slid in under it
+        let shapes = &pieces::load_piece_set("tetrominoes").unwrap().shapes;
+        let mut board = Board::new((10, 20));
+        let i = Tetromino::at_top(&shapes[0], board.width());
+        board.place(i.offset((-3, 10)));
+        board.place(i.offset((1, 10)));
+        let start = Tetromino::at_top(&shapes[1], board.width()).offset((-2, 12));
+        let fewer_holes = Weights {
+            height: 0.0,
+            lines: 0.0,
+            holes: -1.0,
+            bumpiness: 0.0,
+        };
+
+        let placement = best_placement(&board, start, None, &fewer_holes).unwrap();
+        let mut placed = start;
+        for _ in 0..placement.rotations {
+            placed = board.rotated(placed, Rotation::Clockwise).unwrap().0;
+        }
+        let direction = if placement.shift < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        for _ in 0..placement.shift.abs() {
+            placed = board.moved(placed, direction).unwrap();
+        }
+        let placed = board.dropped(placed);
+        assert!(placed.blocks().all(|(x, y)| x < 8 && y > 10));
+    }
+}
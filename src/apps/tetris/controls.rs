@@ -0,0 +1,81 @@
+use crate::input::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    HardDrop,
+    Hold,
+}
+
+/// Which keys control a game. Two players can share a keyboard, each using one half of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    Solo,
+    LeftPlayer,
+    RightPlayer,
+}
+
+impl Controls {
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        match self {
+            Controls::Solo => match code {
+                KeyCode::Char('a') | KeyCode::Left => Some(Action::MoveLeft),
+                KeyCode::Char('d') | KeyCode::Right => Some(Action::MoveRight),
+                KeyCode::Char('s') | KeyCode::Down => Some(Action::SoftDrop),
+                KeyCode::Char('w') | KeyCode::Char('x') | KeyCode::Up => {
+                    Some(Action::RotateClockwise)
+                }
+                KeyCode::Char('z') => Some(Action::RotateCounterClockwise),
+                KeyCode::Space => Some(Action::HardDrop),
+                KeyCode::Char('c') => Some(Action::Hold),
+                _ => None,
+            },
+            Controls::LeftPlayer => match code {
+                KeyCode::Char('a') => Some(Action::MoveLeft),
+                KeyCode::Char('d') => Some(Action::MoveRight),
+                KeyCode::Char('s') => Some(Action::SoftDrop),
+                KeyCode::Char('w') => Some(Action::RotateClockwise),
+                KeyCode::Char('e') => Some(Action::RotateCounterClockwise),
+                KeyCode::Space => Some(Action::HardDrop),
+                KeyCode::Char('c') => Some(Action::Hold),
+                _ => None,
+            },
+            Controls::RightPlayer => match code {
+                KeyCode::Char('j') | KeyCode::Left => Some(Action::MoveLeft),
+                KeyCode::Char('l') | KeyCode::Right => Some(Action::MoveRight),
+                KeyCode::Char('k') | KeyCode::Down => Some(Action::SoftDrop),
+                KeyCode::Char('i') | KeyCode::Up => Some(Action::RotateClockwise),
+                KeyCode::Char('o') => Some(Action::RotateCounterClockwise),
+                KeyCode::Enter => Some(Action::HardDrop),
+                KeyCode::Char('u') => Some(Action::Hold),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn help_text(&self) -> &'static str {
+        match self {
+            Controls::Solo => {
+                "\
+Controls:
+--------
+A / Left: move left
+D / Right: move right
+W / Up / X: rotate clockwise
+Z: rotate counter-clockwise
+S / Down: fall faster
+Space: drop
+C: hold
+"
+            }
+            Controls::LeftPlayer => "A / D: move, S: fall faster\nW / E: rotate\nSpace: drop, C: hold",
+            Controls::RightPlayer => {
+                "J / L: move, K: fall faster\nI / O: rotate\nEnter: drop, U: hold\n(or the arrow keys)"
+            }
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use crate::{translated, Color, Direction, Point};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Piece sets are loaded once, and then kept around for as long as the program runs, so
+/// that tetrominoes can simply point at their shape.
+pub type Shape = &'static ShapeDef;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tetromino {
+    pub origin: Point,
+    pub orientation: Orientation,
+    pub shape: Shape,
+}
+
+/// One kind of piece, e.g. the T tetromino
+#[derive(Debug)]
+pub struct ShapeDef {
+    name: String,
+    /// The cells in each orientation, relative to the top left of the shape's bounding box
+    orientations: [Vec<Point>; 4],
+    /// The width and height of the square that the shape turns within
+    size: i16,
+    color: Color,
+    /// Where the shape spawns, relative to the top of the board with its bounding box
+    /// centered
+    spawn_offset: Point,
+    kicks: Kicks,
+    /// Whether rotating the shape into a tight spot counts as a T-spin
+    pub t_spins: bool,
+}
+
+/// The wall kicks that a shape uses
+#[derive(Debug, Clone, Copy)]
+enum Kicks {
+    /// The ones of the J, L, S, T and Z tetrominoes
+    Standard,
+    /// The ones of the I tetromino
+    I,
+    /// Not kicked at all
+    None,
+}
+
+/// All the shapes that a game is played with.
+///
+/// Parsed from text that describes each piece with a header line followed by its grid of
+/// cells, where '#' is filled and '.' is empty, and with empty lines in between pieces.
+/// The header looks like `piece T 80,80,235 spawn=0,-1 kicks=i t-spins`: the name, the
+/// color, and optionally where it spawns relative to the top center of the board, which
+/// wall kicks it uses ("standard", "i" or "none"), and whether it can T-spin. The grid is
+/// square, and is either the spawn orientation alone, which is then turned clockwise for
+/// the others, or all four orientations side by side, separated by spaces. Lines starting
+/// with '#' in between pieces are comments.
+#[derive(Debug)]
+pub struct PieceSet {
+    pub shapes: Vec<ShapeDef>,
+}
+
+const BUILT_IN_SETS: [(&str, &str); 3] = [
+    ("tetrominoes", include_str!("pieces/tetrominoes.txt")),
+    ("trominoes", include_str!("pieces/trominoes.txt")),
+    ("pentominoes", include_str!("pieces/pentominoes.txt")),
+];
+
+/// Returns one of the built-in piece sets by name, or else reads one from the file at the
+/// path. Sets from files are never freed.
+pub fn load_piece_set(name_or_path: &str) -> Result<&'static PieceSet, String> {
+    static BUILT_IN: OnceLock<Vec<PieceSet>> = OnceLock::new();
+    let built_in = BUILT_IN.get_or_init(|| {
+        BUILT_IN_SETS
+            .iter()
+            .map(|(_, text)| text.parse().expect("Valid built-in piece set"))
+            .collect()
+    });
+    if let Some(i) = BUILT_IN_SETS
+        .iter()
+        .position(|&(name, _)| name == name_or_path)
+    {
+        return Ok(&built_in[i]);
+    }
+
+    let text = std::fs::read_to_string(name_or_path)
+        .map_err(|e| format!("Couldn't read {}: {}", name_or_path, e))?;
+    let set: PieceSet = text
+        .parse()
+        .map_err(|e| format!("{}: {}", name_or_path, e))?;
+    Ok(Box::leak(Box::new(set)))
+}
+
+impl PieceSet {
+    /// The width and height that fits any of the shapes, as shown in the upcoming hint
+    pub fn hint_size(&self) -> (u8, u8) {
+        self.shapes
+            .iter()
+            .map(|shape| {
+                let (width, height) = span(&shape.orientations[0]);
+                (width as u8, height as u8)
+            })
+            .fold((0, 0), |(w, h), (width, height)| {
+                (w.max(width), h.max(height))
+            })
+    }
+
+    /// The size of the largest bounding box, which is how narrow or low the board can be
+    pub fn max_size(&self) -> i16 {
+        self.shapes.iter().map(|shape| shape.size).max().unwrap()
+    }
+
+    /// Checks that every shape fits on a board of the given size, and spawns inside of it
+    pub fn check_board_size(&'static self, width: i16, height: i16) -> Result<(), String> {
+        let min_size = self.max_size();
+        if width < min_size || height < min_size {
+            return Err(format!(
+                "The board has to be at least {}x{} for these pieces",
+                min_size, min_size
+            ));
+        }
+        for shape in &self.shapes {
+            let spawned = Tetromino::at_top(shape, width);
+            let inside = |(x, y): Point| (0..width).contains(&x) && (0..height).contains(&y);
+            if !spawned.blocks().all(inside) {
+                return Err(format!(
+                    "The {} piece would spawn outside of a {}x{} board",
+                    shape.name, width, height
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PieceSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut shapes = vec![];
+        // The line number of the current piece's header, and the lines of its grid
+        let mut current: Option<(usize, &str, Vec<&str>)> = None;
+        for (i, line) in s.lines().chain([""]).enumerate() {
+            let line = line.trim();
+            match &mut current {
+                Some((_, _, rows)) if !line.is_empty() => rows.push(line),
+                Some((number, header, rows)) => {
+                    let shape = ShapeDef::parse(header, rows)
+                        .map_err(|e| format!("piece on line {}: {}", number, e))?;
+                    if shapes
+                        .iter()
+                        .any(|other: &ShapeDef| other.name == shape.name)
+                    {
+                        return Err(format!("more than one piece is named {}", shape.name));
+                    }
+                    shapes.push(shape);
+                    current = None;
+                }
+                None if line.is_empty() || line.starts_with('#') => {}
+                None => current = Some((i + 1, line, vec![])),
+            }
+        }
+        if shapes.is_empty() {
+            return Err("no pieces".to_string());
+        }
+        Ok(Self { shapes })
+    }
+}
+
+impl ShapeDef {
+    fn parse(header: &str, rows: &[&str]) -> Result<Self, String> {
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some("piece") {
+            return Err("expected a line like \"piece <name> <r>,<g>,<b>\"".to_string());
+        }
+        let name = fields.next().ok_or("missing name")?.to_string();
+        let color = fields.next().ok_or("missing color")?;
+        let color = match parse_numbers(color)?[..] {
+            [r, g, b] => (r, g, b),
+            _ => return Err(format!("invalid color: {:?}", color)),
+        };
+        let mut spawn_offset = (0, 0);
+        let mut kicks = Kicks::Standard;
+        let mut t_spins = false;
+        for field in fields {
+            match field.split_once('=') {
+                Some(("spawn", offset)) => {
+                    spawn_offset = match parse_numbers(offset)?[..] {
+                        [x, y] => (x, y),
+                        _ => return Err(format!("invalid spawn offset: {:?}", offset)),
+                    }
+                }
+                Some(("kicks", "standard")) => kicks = Kicks::Standard,
+                Some(("kicks", "i")) => kicks = Kicks::I,
+                Some(("kicks", "none")) => kicks = Kicks::None,
+                None if field == "t-spins" => t_spins = true,
+                _ => return Err(format!("unknown option: {:?}", field)),
+            }
+        }
+
+        let size = rows.len() as i16;
+        let grids: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| row.split_whitespace().collect())
+            .collect();
+        let grid_count = grids.first().ok_or("missing grid")?.len();
+        if grid_count != 1 && grid_count != 4 {
+            return Err("expected either one grid, or four side by side".to_string());
+        }
+        let mut orientations: [Vec<Point>; 4] = Default::default();
+        for (y, row) in grids.iter().enumerate() {
+            if row.len() != grid_count {
+                return Err("every row needs to have the same number of grids".to_string());
+            }
+            for (i, grid_row) in row.iter().enumerate() {
+                if grid_row.chars().count() != rows.len() {
+                    return Err("the grid has to be as wide as it is high".to_string());
+                }
+                for (x, c) in grid_row.chars().enumerate() {
+                    match c {
+                        '#' => orientations[i].push((x as i16, y as i16)),
+                        '.' => {}
+                        _ => return Err(format!("unexpected {:?} in the grid", c)),
+                    }
+                }
+            }
+        }
+        if grid_count == 1 {
+            // Each orientation is the previous one turned a quarter clockwise
+            for i in 1..4 {
+                orientations[i] = orientations[i - 1]
+                    .iter()
+                    .map(|&(x, y)| (size - 1 - y, x))
+                    .collect();
+            }
+        }
+        if orientations.iter().any(|cells| cells.is_empty()) {
+            return Err("every orientation needs at least one cell".to_string());
+        }
+        let top = orientations[0].iter().map(|&(_, y)| y).min().unwrap();
+        if top + spawn_offset.1 < 0 {
+            return Err("the piece would spawn above the board".to_string());
+        }
+
+        Ok(Self {
+            name,
+            orientations,
+            size,
+            color,
+            spawn_offset,
+            kicks,
+            t_spins,
+        })
+    }
+}
+
+fn parse_numbers<T: FromStr>(text: &str) -> Result<Vec<T>, String> {
+    text.split(',')
+        .map(|number| {
+            number
+                .parse()
+                .map_err(|_| format!("invalid number: {:?}", number))
+        })
+        .collect()
+}
+
+/// How many columns and rows the cells stretch over
+fn span(cells: &[Point]) -> (i16, i16) {
+    let min_x = cells.iter().map(|&(x, _)| x).min().unwrap();
+    let max_x = cells.iter().map(|&(x, _)| x).max().unwrap();
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap();
+    let max_y = cells.iter().map(|&(_, y)| y).max().unwrap();
+    (max_x - min_x + 1, max_y - min_y + 1)
+}
+
+impl Tetromino {
+    pub fn at_top(shape: Shape, board_width: i16) -> Self {
+        let x = (board_width - shape.size) / 2;
+        Self {
+            origin: (x + shape.spawn_offset.0, shape.spawn_offset.1),
+            orientation: Orientation::First,
+            shape,
+        }
+    }
+
+    /// Placed in the top left corner of the upcoming hint, and centered horizontally
+    pub fn in_upcoming_hint(shape: Shape, hint_width: u8) -> Self {
+        let cells = &shape.orientations[0];
+        let left = cells.iter().map(|&(x, _)| x).min().unwrap();
+        let top = cells.iter().map(|&(_, y)| y).min().unwrap();
+        let (width, _) = span(cells);
+        Self {
+            origin: ((hint_width as i16 - width) / 2 - left, -top),
+            orientation: Orientation::First,
+            shape,
+        }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = Point> {
+        let origin = self.origin;
+        self.shape.orientations[self.orientation.index()]
+            .iter()
+            .map(move |&(x, y)| (origin.0 + x, origin.1 + y))
+    }
+
+    pub fn color(&self) -> Color {
+        self.shape.color
+    }
+
+    fn resolve(&self, point: Point) -> Point {
+        (self.origin.0 + point.0, self.origin.1 + point.1)
+    }
+
+    pub fn translate(&self, direction: Direction) -> Self {
+        self.offset(translated((0, 0), direction))
+    }
+
+    pub fn offset(&self, offset: Point) -> Self {
+        Self {
+            origin: (self.origin.0 + offset.0, self.origin.1 + offset.1),
+            orientation: self.orientation,
+            shape: self.shape,
+        }
+    }
+
+    pub fn rotated(&self, rotation: Rotation) -> Self {
+        let orientation = match rotation {
+            Rotation::Clockwise => self.orientation.rotated_clockwise(),
+            Rotation::CounterClockwise => self.orientation.rotated_counter_clockwise(),
+        };
+        Self {
+            origin: self.origin,
+            orientation,
+            shape: self.shape,
+        }
+    }
+
+    /// The two corners of the bounding box on the side a T points to, and the two on the
+    /// opposite side
+    pub fn t_corners(&self) -> ([Point; 2], [Point; 2]) {
+        // Clockwise, starting from the top left
+        let last = self.shape.size - 1;
+        let corners = [(0, 0), (last, 0), (last, last), (0, last)].map(|p| self.resolve(p));
+        let i = self.orientation.index();
+        (
+            [corners[i], corners[(i + 1) % 4]],
+            [corners[(i + 2) % 4], corners[(i + 3) % 4]],
+        )
+    }
+
+    /// The offsets to try, in order, when rotating from the current orientation
+    pub fn kicks(&self, rotation: Rotation) -> [Point; 5] {
+        let table = match self.shape.kicks {
+            Kicks::Standard => &JLSTZ_KICKS,
+            Kicks::I => &I_KICKS,
+            Kicks::None => &NO_KICKS,
+        };
+        match rotation {
+            Rotation::Clockwise => table[self.orientation.index()],
+            // Rotating back undoes the kicks of the clockwise rotation
+            Rotation::CounterClockwise => {
+                let from = self.orientation.rotated_counter_clockwise();
+                table[from.index()].map(|(x, y)| (-x, -y))
+            }
+        }
+    }
+}
+
+// Wall kicks for clockwise rotations, indexed by the orientation that is rotated from.
+// These are the standard SRS tables, with y pointing down.
+const JLSTZ_KICKS: [[Point; 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+];
+const I_KICKS: [[Point; 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)],
+    [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)],
+    [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)],
+];
+const NO_KICKS: [[Point; 5]; 4] = [[(0, 0); 5]; 4];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Orientation {
+    First,
+    Second,
+    Third,
+    Fourth,
+}
+
+impl Orientation {
+    pub fn rotated_clockwise(&self) -> Self {
+        match self {
+            Orientation::First => Orientation::Second,
+            Orientation::Second => Orientation::Third,
+            Orientation::Third => Orientation::Fourth,
+            Orientation::Fourth => Orientation::First,
+        }
+    }
+
+    pub fn rotated_counter_clockwise(&self) -> Self {
+        match self {
+            Orientation::First => Orientation::Fourth,
+            Orientation::Second => Orientation::First,
+            Orientation::Third => Orientation::Second,
+            Orientation::Fourth => Orientation::Third,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Orientation::First => 0,
+            Orientation::Second => 1,
+            Orientation::Third => 2,
+            Orientation::Fourth => 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::tetris::board::Board;
+
+    fn leaked(text: &str) -> &'static PieceSet {
+        Box::leak(Box::new(text.parse().unwrap()))
+    }
+
+    #[test]
+    fn pieces_have_to_fit_on_the_board() {
+        let tall = leaked("piece P 200,200,200\n#..\n#..\n#..\n");
+        assert!(tall.check_board_size(3, 3).is_ok());
+        assert!(tall.check_board_size(3, 2).is_err());
+        assert!(tall.check_board_size(2, 3).is_err());
+
+        let off_to_the_side = leaked("piece P 200,200,200 spawn=8,0\n##\n##\n");
+        assert!(off_to_the_side.check_board_size(10, 20).is_err());
+        assert!(off_to_the_side.check_board_size(20, 20).is_ok());
+    }
+
+    #[test]
+    fn t_is_kicked_off_the_wall() {
+        let t = &load_piece_set("tetrominoes").unwrap().shapes[2];
+        let board = Board::new((10, 20));
+        // Pointing right, with its stem against the left wall
+        let against_wall = Tetromino {
+            origin: (-1, 10),
+            orientation: Orientation::Second,
+            shape: t,
+        };
+        assert!(!board.would_collide(against_wall));
+
+        // Pointing up again doesn't fit in place, so the first kick moves it to the right
+        let (rotated, kick) = board
+            .rotated(against_wall, Rotation::CounterClockwise)
+            .unwrap();
+        assert_eq!(kick, 1);
+        assert_eq!(rotated.origin, (0, 10));
+        assert_eq!(rotated.orientation.index(), Orientation::First.index());
+    }
+
+    #[test]
+    fn counter_clockwise_kicks_undo_clockwise_ones() {
+        let sets = BUILT_IN_SETS.map(|(name, _)| load_piece_set(name).unwrap());
+        let orientations = [
+            Orientation::First,
+            Orientation::Second,
+            Orientation::Third,
+            Orientation::Fourth,
+        ];
+        for shape in sets.iter().flat_map(|set| &set.shapes) {
+            for orientation in orientations {
+                let tetromino = Tetromino {
+                    origin: (3, 5),
+                    orientation,
+                    shape,
+                };
+                let clockwise = tetromino.kicks(Rotation::Clockwise);
+                for (i, &kick) in clockwise.iter().enumerate() {
+                    let rotated = tetromino.rotated(Rotation::Clockwise).offset(kick);
+                    let back = rotated.kicks(Rotation::CounterClockwise)[i];
+                    let back = rotated.rotated(Rotation::CounterClockwise).offset(back);
+                    assert_eq!(back.origin, tetromino.origin, "{} kick {}", shape.name, i);
+                    assert_eq!(back.orientation.index(), orientation.index());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::cmp::min;
+
+/// How many frames it takes a tetromino to fall by one row, by level. Levels past the end of
+/// the table use its last entry.
+///
+/// This is the guideline's gravity curve, rounded to whole frames at 30 frames per second.
+const GRAVITY: [u32; 11] = [30, 24, 19, 14, 11, 8, 6, 4, 3, 2, 1];
+
+const LINES_PER_LEVEL: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TSpin {
+    /// Only one of the two corners in front of the T is filled
+    Mini,
+    Full,
+}
+
+/// Keeps the score, level and line count, following the Tetris guideline.
+pub struct Scoring {
+    pub score: u32,
+    pub level: u32,
+    pub lines: u32,
+    /// Whether the level goes up as lines are cleared
+    progression: bool,
+    /// A description of the last clear that was worth mentioning, like "Tetris!"
+    pub last_clear: Option<String>,
+    /// How many times in a row a locked tetromino has cleared lines, minus one
+    combo: Option<u32>,
+    /// Whether the last clear was a difficult one: a tetris, or a T-spin that cleared lines
+    back_to_back: bool,
+}
+
+impl Scoring {
+    pub fn new(progression: bool) -> Self {
+        Self {
+            score: 0,
+            level: 1,
+            lines: 0,
+            progression,
+            last_clear: None,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    /// How many frames it takes a tetromino to fall by one row, at the current level
+    pub fn fall_delay(&self) -> u32 {
+        GRAVITY[min(self.level as usize, GRAVITY.len()) - 1]
+    }
+
+    /// Scores a soft or hard drop of a number of rows
+    pub fn add_drop(&mut self, rows: u32, hard: bool) {
+        self.score += if hard { 2 * rows } else { rows };
+    }
+
+    /// Scores a tetromino that locked, clearing some lines
+    pub fn add_lock(&mut self, lines: u32, t_spin: Option<TSpin>) {
+        let points = match (t_spin, lines) {
+            (None, 0) => 0,
+            (None, 1) => 100,
+            (None, 2) => 300,
+            (None, 3) => 500,
+            (None, _) => 800,
+            (Some(TSpin::Mini), 0) => 100,
+            (Some(TSpin::Mini), 1) => 200,
+            (Some(TSpin::Mini), _) => 400,
+            (Some(TSpin::Full), 0) => 400,
+            (Some(TSpin::Full), 1) => 800,
+            (Some(TSpin::Full), 2) => 1200,
+            (Some(TSpin::Full), _) => 1600,
+        };
+        let mut points = points * self.level;
+        let mut description = describe_clear(lines, t_spin);
+
+        if lines > 0 {
+            let difficult = lines >= 4 || t_spin.is_some();
+            if difficult && self.back_to_back {
+                points = points * 3 / 2;
+                description = description.map(|text| format!("{}\nBack-to-back!", text));
+            }
+            self.back_to_back = difficult;
+
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            if combo > 0 {
+                points += 50 * combo * self.level;
+                description = description.map(|text| format!("{}\nCombo x{}", text, combo));
+            }
+            self.combo = Some(combo);
+        } else {
+            self.combo = None;
+        }
+
+        self.score += points;
+        self.lines += lines;
+        if self.progression {
+            self.level = 1 + self.lines / LINES_PER_LEVEL;
+        }
+        if description.is_some() {
+            self.last_clear = description;
+        }
+    }
+}
+
+fn describe_clear(lines: u32, t_spin: Option<TSpin>) -> Option<String> {
+    let lines_name = match lines {
+        1 => "single",
+        2 => "double",
+        _ => "triple",
+    };
+    let description = match (t_spin, lines) {
+        (None, 0) => return None,
+        (None, 1) => "Single".to_string(),
+        (None, 2) => "Double".to_string(),
+        (None, 3) => "Triple".to_string(),
+        (None, _) => "Tetris!".to_string(),
+        (Some(TSpin::Mini), 0) => "Mini T-spin".to_string(),
+        (Some(TSpin::Mini), _) => format!("Mini T-spin {}", lines_name),
+        (Some(TSpin::Full), 0) => "T-spin".to_string(),
+        (Some(TSpin::Full), _) => format!("T-spin {}", lines_name),
+    };
+    Some(description)
+}
+
+/// How many rows of garbage a clear sends to the opponent in versus mode
+pub fn garbage_to_send(lines: u32, t_spin: Option<TSpin>) -> u32 {
+    match (t_spin, lines) {
+        (_, 0) => 0,
+        (Some(TSpin::Full), lines) => 2 * lines,
+        (_, 1) => 0,
+        (_, 2) => 1,
+        (_, 3) => 2,
+        (_, _) => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The points for each lock, which start out at some level without progression
+    fn points(level: u32, locks: &[(u32, Option<TSpin>)]) -> Vec<u32> {
+        let mut scoring = Scoring::new(false);
+        scoring.level = level;
+        locks
+            .iter()
+            .map(|&(lines, t_spin)| {
+                let before = scoring.score;
+                scoring.add_lock(lines, t_spin);
+                scoring.score - before
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_clears_are_worth_more_at_higher_levels() {
+        // Locking without clearing anything in between, so that there are no combos
+        let clears = [(1, None), (0, None), (2, None), (0, None), (3, None)];
+        assert_eq!(points(1, &clears), [100, 0, 300, 0, 500]);
+        assert_eq!(points(4, &clears), [400, 0, 1200, 0, 2000]);
+        assert_eq!(points(1, &[(4, None)]), [800]);
+        assert_eq!(points(4, &[(4, None)]), [3200]);
+    }
+
+    #[test]
+    fn back_to_back_tetrises_are_worth_half_again() {
+        let tetrises = [(4, None), (0, None), (4, None), (0, None), (4, None)];
+        assert_eq!(points(1, &tetrises), [800, 0, 1200, 0, 1200]);
+        // Anything easier in between breaks the chain
+        let broken = [(4, None), (0, None), (1, None), (0, None), (4, None)];
+        assert_eq!(points(1, &broken), [800, 0, 100, 0, 800]);
+    }
+
+    #[test]
+    fn combos_add_up_until_a_lock_clears_nothing() {
+        let singles = [(1, None), (1, None), (1, None), (0, None), (1, None)];
+        assert_eq!(points(1, &singles), [100, 150, 200, 0, 100]);
+        assert_eq!(points(2, &singles), [200, 300, 400, 0, 200]);
+
+        let mut scoring = Scoring::new(false);
+        for _ in 0..3 {
+            scoring.add_lock(1, None);
+        }
+        assert_eq!(scoring.last_clear.as_deref(), Some("Single\nCombo x2"));
+    }
+
+    #[test]
+    fn full_t_spins_are_worth_more_than_minis() {
+        let mini = Some(TSpin::Mini);
+        let full = Some(TSpin::Full);
+        let spins = [(0, mini), (0, full)];
+        assert_eq!(points(1, &spins), [100, 400]);
+        assert_eq!(points(1, &[(1, mini)]), [200]);
+        assert_eq!(points(1, &[(1, full)]), [800]);
+        assert_eq!(points(1, &[(2, full)]), [1200]);
+    }
+
+    #[test]
+    fn the_level_goes_up_every_ten_lines() {
+        let mut scoring = Scoring::new(true);
+        for _ in 0..2 {
+            scoring.add_lock(4, None);
+            scoring.add_lock(0, None);
+        }
+        assert_eq!((scoring.level, scoring.lines), (1, 8));
+        scoring.add_lock(2, None);
+        assert_eq!((scoring.level, scoring.lines), (2, 10));
+        assert_eq!(scoring.fall_delay(), GRAVITY[1]);
+
+        let before = scoring.score;
+        scoring.add_lock(0, None);
+        scoring.add_lock(1, None);
+        assert_eq!(scoring.score - before, 200);
+    }
+}
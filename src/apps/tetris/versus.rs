@@ -0,0 +1,190 @@
+use super::controls::Controls;
+use super::{Tetris, TetrisOptions};
+use crate::apps::{App, AppContext, AppStatus};
+use crate::input::KeyEvent;
+use crate::{Cell, Color, Graphics, GraphicsBuf, PanelItem, SidePanel};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const DIVIDER: Color = (60, 60, 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// The player that is still standing
+    Win(usize),
+    Draw,
+}
+
+/// Two games of Tetris side by side, on one keyboard. Whoever tops out first loses, and if
+/// both do in the same frame, it's a draw.
+pub struct Versus {
+    players: [Tetris; 2],
+    rng: StdRng,
+    graphics: Graphics,
+}
+
+impl Versus {
+    pub fn new(ctx: AppContext, options: &TetrisOptions) -> Self {
+        let mut rng = ctx.rng;
+        // Both players are dealt the same tetrominoes, in the same order
+        let seed: u64 = rng.gen();
+        let player_context = || AppContext {
+            rng: StdRng::seed_from_u64(seed),
+        };
+        // With the autopilot on, the bot plays against whoever is on the left
+        let left_options = TetrisOptions {
+            autopilot: false,
+            ..options.clone()
+        };
+        let players = [
+            Tetris::with_controls(player_context(), &left_options, Controls::LeftPlayer),
+            Tetris::with_controls(player_context(), options, Controls::RightPlayer),
+        ];
+
+        let (width, height) = players[0].graphics.buf.dimensions();
+        let graphics = Graphics::new(
+            "Tetris: Versus".to_string(),
+            Some(SidePanel { items: vec![] }),
+            GraphicsBuf::new((width * 2 + 1, height)),
+        );
+        let mut this = Self {
+            players,
+            rng,
+            graphics,
+        };
+        this.update_graphics();
+        this
+    }
+
+    /// How the game ended, once either player has topped out
+    fn outcome(&self) -> Option<Outcome> {
+        match [self.players[0].is_over(), self.players[1].is_over()] {
+            [false, false] => None,
+            [true, true] => Some(Outcome::Draw),
+            [true, false] => Some(Outcome::Win(1)),
+            [false, true] => Some(Outcome::Win(0)),
+        }
+    }
+
+    fn send_garbage(&mut self) {
+        let width = self.players[0].board.width();
+        for i in 0..2 {
+            let rows = std::mem::take(&mut self.players[i].outgoing_garbage);
+            if rows > 0 {
+                let gap = self.rng.gen_range(0..width);
+                self.players[1 - i].incoming_garbage.push_back((rows, gap));
+            }
+        }
+    }
+
+    fn update_graphics(&mut self) {
+        let (width, height) = self.players[0].graphics.buf.dimensions();
+        for (i, player) in self.players.iter().enumerate() {
+            let x_offset = i as i16 * (width as i16 + 1);
+            for y in 0..height as i16 {
+                for x in 0..width as i16 {
+                    let cell = player.graphics.buf.get((x, y)).unwrap();
+                    self.graphics.buf.set((x_offset + x, y), cell);
+                }
+            }
+        }
+        for y in 0..height as i16 {
+            self.graphics
+                .buf
+                .set((width as i16, y), Cell::Colored(DIVIDER));
+        }
+
+        let mut items = vec![];
+        for (i, player) in self.players.iter().enumerate() {
+            let name = ["Player 1 (left)", "Player 2 (right)"][i];
+            let mut player_items = player.graphics.side_panel.clone().unwrap().items;
+            if let PanelItem::TextItem { text } = &mut player_items[0] {
+                *text = format!("{}\n{}", name, text);
+            }
+            items.extend(player_items);
+        }
+        let result = match self.outcome() {
+            Some(Outcome::Win(winner)) => Some(format!("Player {} wins!", winner + 1)),
+            Some(Outcome::Draw) => Some("It's a draw!".to_string()),
+            None => None,
+        };
+        if let Some(text) = result {
+            items.push(PanelItem::TextItem { text });
+        }
+        self.graphics.side_panel = Some(SidePanel { items });
+    }
+}
+
+impl App for Versus {
+    fn run_frame(&mut self) {
+        if self.outcome().is_some() {
+            // Only the top-out animations keep going
+            for player in &mut self.players {
+                if player.is_over() {
+                    player.run_frame();
+                }
+            }
+            self.update_graphics();
+            return;
+        }
+        for player in &mut self.players {
+            player.run_frame();
+        }
+        self.send_garbage();
+        self.update_graphics();
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if self.outcome().is_some() {
+            return;
+        }
+        // Each player only reacts to their own keys
+        for player in &mut self.players {
+            player.handle_key_event(event);
+        }
+        self.send_garbage();
+        self.update_graphics();
+    }
+
+    fn graphics(&self) -> &Graphics {
+        &self.graphics
+    }
+
+    fn status(&self) -> AppStatus {
+        // The winner's score is the one that goes on the high score table, and a draw has
+        // no winner
+        match self.outcome() {
+            Some(Outcome::Win(winner)) => AppStatus::Won {
+                score: self.players[winner].scoring.score,
+            },
+            Some(Outcome::Draw) => AppStatus::GameOver { score: 0 },
+            None => AppStatus::Running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Args, Command, FromArgMatches};
+
+    #[test]
+    fn topping_out_together_is_a_draw() {
+        let matches = TetrisOptions::augment_args(Command::new("tetris"))
+            .try_get_matches_from(["tetris", "--mode", "versus"])
+            .unwrap();
+        let options = TetrisOptions::from_arg_matches(&matches).unwrap();
+        let mut versus = Versus::new(
+            AppContext {
+                rng: StdRng::seed_from_u64(0),
+            },
+            &options,
+        );
+        // Without any input, both players are dealt and drop the same tetrominoes
+        while versus.outcome().is_none() {
+            versus.run_frame();
+        }
+        assert_eq!(versus.outcome(), Some(Outcome::Draw));
+        assert_eq!(versus.status(), AppStatus::GameOver { score: 0 });
+    }
+}
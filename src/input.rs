@@ -0,0 +1,187 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A key press, repeat or release, independent of the runtime that produced it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+    pub kind: KeyEventKind,
+}
+
+impl KeyEvent {
+    pub fn new(code: KeyCode, modifiers: Modifiers, kind: KeyEventKind) -> Self {
+        Self {
+            code,
+            modifiers,
+            kind,
+        }
+    }
+
+    /// True for both the initial press and any repeats generated while the key is held.
+    pub fn is_press(&self) -> bool {
+        matches!(self.kind, KeyEventKind::Press | KeyEventKind::Repeat)
+    }
+
+    pub fn is_release(&self) -> bool {
+        self.kind == KeyEventKind::Release
+    }
+}
+
+/// Letters are always lowercase; a held shift key is reported through [Modifiers].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyCode {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyEventKind {
+    Press,
+    Repeat,
+    Release,
+}
+
+/// Parses a key on the form "a", "space", "ctrl+shift+left" or "ctrl++", as written by
+/// [format_key].
+pub fn parse_key(s: &str) -> Result<(KeyCode, Modifiers), String> {
+    let s = s.trim();
+    // The key itself can be '+', in which case it's the last one of two in a row
+    let (modifier_part, code) = match s.strip_suffix('+') {
+        Some(rest) if rest.is_empty() || rest.ends_with('+') => {
+            (&rest[..rest.len().saturating_sub(1)], "+")
+        }
+        _ => s.rsplit_once('+').unwrap_or(("", s)),
+    };
+    let code = code.parse()?;
+    let mut modifiers = Modifiers::default();
+    if modifier_part.is_empty() {
+        return Ok((code, modifiers));
+    }
+    for part in modifier_part.split('+') {
+        match &part.to_lowercase()[..] {
+            "shift" => modifiers.shift = true,
+            "ctrl" => modifiers.ctrl = true,
+            "alt" => modifiers.alt = true,
+            unknown => return Err(format!("Unknown modifier: {:?}", unknown)),
+        }
+    }
+    Ok((code, modifiers))
+}
+
+/// Writes a key such that [parse_key] can read it back
+pub fn format_key(code: KeyCode, modifiers: Modifiers) -> String {
+    let mut key = String::new();
+    if modifiers.ctrl {
+        key.push_str("ctrl+");
+    }
+    if modifiers.alt {
+        key.push_str("alt+");
+    }
+    if modifiers.shift {
+        key.push_str("shift+");
+    }
+    format!("{}{}", key, code)
+}
+
+impl FromStr for KeyCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = match &s.to_lowercase()[..] {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Space,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Escape,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            other => {
+                let mut chars = other.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(format!("Unknown key: {:?}", s)),
+                }
+            }
+        };
+        Ok(code)
+    }
+}
+
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyCode::Char(ch) => write!(f, "{}", ch),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Space => write!(f, "space"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Escape => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_can_be_parsed_back() {
+        let codes = [
+            KeyCode::Char('a'),
+            KeyCode::Char('1'),
+            KeyCode::Char('+'),
+            KeyCode::Char('-'),
+            KeyCode::Char('#'),
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Space,
+            KeyCode::Enter,
+            KeyCode::Escape,
+            KeyCode::Tab,
+            KeyCode::Backspace,
+        ];
+        for code in codes {
+            for i in 0..8 {
+                let modifiers = Modifiers {
+                    shift: i & 1 != 0,
+                    ctrl: i & 2 != 0,
+                    alt: i & 4 != 0,
+                };
+                let key = format_key(code, modifiers);
+                assert_eq!(parse_key(&key), Ok((code, modifiers)), "{:?}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert!(parse_key("").is_err());
+        assert!(parse_key("ctrl+").is_err());
+        assert!(parse_key("hyper+a").is_err());
+        assert!(parse_key("abc").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::apps::{App, AppEntry, AppStatus, RunConfig};
+use crate::input::{KeyCode, KeyEvent};
+use crate::scores::{Category, HighScores};
+use crate::session::Session;
+use crate::{Cell, Color, Graphics, GraphicsBuf, PanelItem, SidePanel};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub const RUN_CONFIG: RunConfig = RunConfig { frame_rate: 30 };
+
+/// A menu that lists all games and runs the selected one. Quitting a game returns to the menu.
+pub struct Launcher {
+    entries: &'static [AppEntry],
+    selected: usize,
+    running: Option<(Session, RunConfig)>,
+    rng: StdRng,
+    high_scores: Rc<RefCell<HighScores>>,
+    graphics: Graphics,
+    quit_requested: bool,
+}
+
+const ENTRY_WIDTH: u8 = 16;
+const ENTRY_COLOR: Color = (80, 80, 120);
+const SELECTED_COLOR: Color = (150, 150, 250);
+
+impl Launcher {
+    pub fn new(
+        entries: &'static [AppEntry],
+        rng: StdRng,
+        high_scores: Rc<RefCell<HighScores>>,
+    ) -> Self {
+        let buf = GraphicsBuf::new((ENTRY_WIDTH, entries.len() as u8 * 2 + 1));
+        let graphics = Graphics::new(
+            "Tiny games".to_string(),
+            Some(SidePanel {
+                items: vec![
+                    PanelItem::TextItem {
+                        text: "".to_string(),
+                    },
+                    PanelItem::TextItem {
+                        text: "".to_string(),
+                    },
+                    PanelItem::TextItem {
+                        text: "W/S or Up/Down: select\nEnter: play\nQ: quit".to_string(),
+                    },
+                ],
+            }),
+            buf,
+        );
+        let mut this = Self {
+            entries,
+            selected: 0,
+            running: None,
+            rng,
+            high_scores,
+            graphics,
+            quit_requested: false,
+        };
+        this.update_menu();
+        this
+    }
+
+    fn update_menu(&mut self) {
+        for (i, _) in self.entries.iter().enumerate() {
+            let color = if i == self.selected {
+                SELECTED_COLOR
+            } else {
+                ENTRY_COLOR
+            };
+            for x in 1..ENTRY_WIDTH - 1 {
+                let y = i as i16 * 2 + 1;
+                self.graphics.buf.set((x as i16, y), Cell::Colored(color));
+            }
+        }
+
+        let list = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let marker = if i == self.selected { ">" } else { " " };
+                format!("{} {}", marker, entry.name)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        let side_panel = self.graphics.side_panel.as_mut().unwrap();
+        *side_panel.unwrap_text_item_mut(0) = list;
+        *side_panel.unwrap_text_item_mut(1) = self.entries[self.selected].description.to_string();
+    }
+
+    fn launch_selected(&mut self) {
+        let entry = &self.entries[self.selected];
+        let rng = StdRng::from_rng(&mut self.rng).unwrap();
+        // Games launched from the menu always run with their default options
+        let constructor = entry
+            .parse_args(&[])
+            .unwrap_or_else(|e| panic!("Couldn't launch {}: {}", entry.name, e));
+        let session = Session::new(constructor, rng)
+            .with_high_scores(self.high_scores.clone(), Category::new(entry.name, &[]));
+        self.running = Some((session, entry.run_config));
+    }
+}
+
+impl App for Launcher {
+    fn run_frame(&mut self) {
+        if let Some((session, _)) = &mut self.running {
+            session.run_frame();
+            if session.status() == AppStatus::RequestQuit {
+                self.running = None;
+            }
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if let Some((session, _)) = &mut self.running {
+            session.handle_key_event(event);
+            if session.status() == AppStatus::RequestQuit {
+                self.running = None;
+            }
+            return;
+        }
+
+        if !event.is_press() {
+            return;
+        }
+        match event.code {
+            KeyCode::Char('w') | KeyCode::Up => {
+                self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+            }
+            KeyCode::Char('s') | KeyCode::Down => {
+                self.selected = (self.selected + 1) % self.entries.len();
+            }
+            KeyCode::Enter | KeyCode::Space => self.launch_selected(),
+            KeyCode::Char('q') | KeyCode::Escape => self.quit_requested = true,
+            _ => {}
+        }
+        self.update_menu();
+    }
+
+    fn graphics(&self) -> &Graphics {
+        match &self.running {
+            Some((session, _)) => session.graphics(),
+            None => &self.graphics,
+        }
+    }
+
+    fn status(&self) -> AppStatus {
+        if self.quit_requested {
+            AppStatus::RequestQuit
+        } else {
+            AppStatus::Running
+        }
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.running
+            .as_ref()
+            .map(|(session, run_config)| session.frame_rate().unwrap_or(run_config.frame_rate))
+    }
+}
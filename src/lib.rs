@@ -0,0 +1,167 @@
+//! Tiny grid games and the runtimes that play them.
+//!
+//! A game implements [App] and draws itself into a [Graphics]: a main [GraphicsBuf] of
+//! colored cells, plus an optional [SidePanel] of text and smaller buffers. Since the games
+//! know nothing about how they're rendered, any of them can be played with any of the
+//! runtimes in [ui]. See `examples/` for a game defined outside of this crate.
+
+extern crate backtrace;
+extern crate crossterm;
+extern crate rand;
+extern crate tui;
+
+pub mod apps;
+pub mod input;
+pub mod launcher;
+pub mod replay;
+pub mod scores;
+pub mod session;
+pub mod ui;
+
+pub use apps::{App, AppContext, AppStatus, RunConfig};
+
+pub type Point = (i16, i16);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Left => Direction::Right,
+            Direction::Down => Direction::Up,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+pub type Color = (u8, u8, u8);
+
+pub fn translated(point: Point, direction: Direction) -> Point {
+    let (dx, dy) = match direction {
+        Direction::Up => (0, -1),
+        Direction::Left => (-1, 0),
+        Direction::Down => (0, 1),
+        Direction::Right => (1, 0),
+    };
+    (point.0 + dx, point.1 + dy)
+}
+
+#[derive(Debug, Clone)]
+pub struct Graphics {
+    pub title: String,
+    side_panel: Option<SidePanel>,
+    pub buf: GraphicsBuf,
+}
+
+impl Graphics {
+    pub fn new(title: String, side_panel: Option<SidePanel>, graphics: GraphicsBuf) -> Self {
+        Self {
+            title,
+            side_panel,
+            buf: graphics,
+        }
+    }
+
+    pub fn side_panel(&self) -> Option<&SidePanel> {
+        self.side_panel.as_ref()
+    }
+
+    pub fn side_panel_mut(&mut self) -> Option<&mut SidePanel> {
+        self.side_panel.as_mut()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SidePanel {
+    pub items: Vec<PanelItem>,
+}
+
+impl SidePanel {
+    pub fn unwrap_graphics_item_mut(&mut self, index: usize) -> &mut GraphicsBuf {
+        match &mut self.items[index] {
+            PanelItem::TextItem { .. } => panic!("Expected graphics"),
+            PanelItem::GraphicsItem { buf } => buf,
+        }
+    }
+
+    pub fn unwrap_text_item_mut(&mut self, index: usize) -> &mut String {
+        match &mut self.items[index] {
+            PanelItem::TextItem { text } => text,
+            PanelItem::GraphicsItem { .. } => panic!("Expected text"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PanelItem {
+    TextItem { text: String },
+    GraphicsItem { buf: GraphicsBuf },
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphicsBuf {
+    buf: Vec<Cell>,
+    dimensions: (u8, u8),
+}
+
+impl GraphicsBuf {
+    pub fn new(dimensions: (u8, u8)) -> Self {
+        Self {
+            buf: vec![Default::default(); dimensions.0 as usize * dimensions.1 as usize],
+            dimensions,
+        }
+    }
+
+    pub fn dimensions(&self) -> (u8, u8) {
+        self.dimensions
+    }
+
+    pub fn set(&mut self, point: Point, value: Cell) {
+        let i = self.buf_index(point).unwrap();
+        self.buf[i] = value;
+    }
+
+    pub fn set_by_index(&mut self, index: usize, value: Cell) {
+        self.buf[index] = value;
+    }
+
+    pub fn get(&self, point: (i16, i16)) -> Option<Cell> {
+        self.buf_index(point).map(|i| self.buf[i])
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Cell {
+        self.buf[index]
+    }
+
+    fn buf_index(&self, pos: (i16, i16)) -> Option<usize> {
+        if pos.0 >= 0
+            && pos.1 >= 0
+            && pos.0 < self.dimensions.0 as i16
+            && pos.1 < self.dimensions.1 as i16
+        {
+            Some(pos.1 as usize * self.dimensions.0 as usize + pos.0 as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Blank,
+    Colored(Color),
+}
+
+impl Cell {
+    pub fn filled() -> Self {
+        Self::Colored((255, 255, 255))
+    }
+}
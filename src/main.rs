@@ -1,50 +1,66 @@
-extern crate backtrace;
-extern crate crossterm;
-extern crate rand;
-extern crate tui;
-
-mod apps;
-mod ui;
-
-use crate::apps::RunConfig;
-use apps::conway::Conway;
-use apps::noise::Noise;
-use apps::particles::Particles;
-use apps::race::Race;
-use apps::snake::Snake;
-use apps::tetris::Tetris;
-use apps::App;
-use ui::debug;
-use ui::terminal;
-use ui::window;
-
-use clap::Parser;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::fs::File;
+use std::path::PathBuf;
+use std::rc::Rc;
+use terminal_games::apps::{self, AppEntry};
+use terminal_games::launcher::{self, Launcher};
+use terminal_games::replay::{Recorder, Replay, Replayer};
+use terminal_games::scores::{Category, HighScores};
+use terminal_games::session::Session;
+use terminal_games::ui::debug;
+use terminal_games::ui::headless::{self, HeadlessConfig};
+use terminal_games::ui::terminal;
+use terminal_games::ui::window;
+use terminal_games::{App, RunConfig};
+
+use clap::{Arg, ArgMatches, Command, CommandFactory, FromArgMatches, Parser};
 
 /// Tiny games, played in a window or right in your terminal!
+///
+/// Pick a game by naming it, followed by any options of its own, or leave it out to pick
+/// one from a menu.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// What game to run
-    #[arg(value_enum, default_value = "tetris")]
-    app: AppName,
-
     /// How to run the game
-    #[arg(short, long, value_enum, default_value = "terminal")]
+    #[arg(short, long, value_enum, default_value = "terminal", global = true)]
     runtime: Runtime,
 
     /// In the terminal, how many characters wide should each game cell be
-    #[arg(short, long, default_value = "3")]
+    #[arg(short, long, default_value = "3", global = true)]
     cell_width: u16,
-}
 
-#[derive(clap::ValueEnum, Clone, Debug)]
-enum AppName {
-    Tetris,
-    Snake,
-    Conway,
-    Noise,
-    Race,
-    Particles,
+    /// Seed for all randomness in the game. Picked at random if not given.
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Record all input to this file, so that the session can be replayed later
+    #[arg(long, global = true)]
+    record: Option<String>,
+
+    /// Feed the inputs from a recorded (or hand-written) file into the game, at the same
+    /// frames. The game and seed are taken from the file, if it specifies them.
+    #[arg(long, conflicts_with = "record", global = true)]
+    replay: Option<String>,
+
+    /// In the headless runtime, how many frames to run
+    #[arg(long, default_value = "100", global = true)]
+    frames: u32,
+
+    /// In the headless runtime, dump the graphics every k frames instead of only the last one
+    #[arg(long, global = true)]
+    dump_every: Option<u32>,
+
+    /// File to keep the high scores in. Defaults to terminal-games/scores.txt in your data
+    /// directory.
+    #[arg(long, global = true)]
+    scores_file: Option<PathBuf>,
+
+    /// In the headless runtime, write the graphics to this file instead of stdout
+    #[arg(short, long, global = true)]
+    output: Option<String>,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -52,39 +68,99 @@ enum Runtime {
     Window,
     Terminal,
     Debug,
+    Headless,
 }
 
 fn main() {
-    let args = Args::parse();
+    let command = apps::APPS.iter().fold(Args::command(), |command, entry| {
+        command.subcommand(entry.command())
+    });
+    let command = command.subcommand(
+        Command::new("scores")
+            .about("Show the high scores")
+            .arg(Arg::new("app").help("Only show the scores of this game")),
+    );
+    let matches = command.get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let load_high_scores = || {
+        let Some(path) = args.scores_file.clone().or_else(HighScores::default_path) else {
+            return HighScores::in_memory();
+        };
+        HighScores::load(path).unwrap_or_else(|e| {
+            eprintln!("Invalid high scores: {}", e);
+            std::process::exit(1);
+        })
+    };
 
-    let app_name = args.app;
-    let runtime = args.runtime;
+    if let Some(("scores", sub_matches)) = matches.subcommand() {
+        print_high_scores(&load_high_scores(), sub_matches);
+        return;
+    }
 
-    let (app, run_config): (Box<dyn App>, RunConfig) = match app_name {
-        AppName::Conway => {
-            let (app, run_config) = Conway::new();
-            (Box::new(app), run_config)
-        }
-        AppName::Noise => {
-            let (app, run_config) = Noise::new();
-            (Box::new(app), run_config)
-        }
-        AppName::Snake => {
-            let (app, run_config) = Snake::new();
-            (Box::new(app), run_config)
+    // The chosen app, along with the options that were given to it
+    let mut app_args: Option<(&AppEntry, Vec<String>)> =
+        matches.subcommand().map(|(name, sub_matches)| {
+            let entry = apps::find_app(name).unwrap();
+            (entry, apps::explicit_args(&entry.command(), sub_matches))
+        });
+    let runtime = args.runtime;
+    let mut seed = args.seed;
+
+    let replay = args.replay.map(|path| {
+        let replay = Replay::load(&path).unwrap_or_else(|e| {
+            eprintln!("Invalid replay: {}", e);
+            std::process::exit(1);
+        });
+        if let Some((name, recorded_args)) = &replay.app {
+            let Some(entry) = apps::find_app(name) else {
+                eprintln!("Invalid replay: unknown app {:?}", name);
+                std::process::exit(1);
+            };
+            app_args = Some((entry, recorded_args.clone()));
         }
-        AppName::Tetris => {
-            let (app, run_config) = Tetris::new();
-            (Box::new(app), run_config)
+        seed = replay.seed.or(seed);
+        replay
+    });
+
+    // Replayed games still ask for a name, so that the recorded input lines up, but their
+    // scores are only kept until the replay ends.
+    let high_scores = Rc::new(RefCell::new(if replay.is_some() {
+        HighScores::in_memory()
+    } else {
+        load_high_scores()
+    }));
+
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let rng = StdRng::seed_from_u64(seed);
+    let (app, run_config): (Box<dyn App>, RunConfig) = match &app_args {
+        Some((entry, args)) => {
+            let constructor = entry.parse_args(args).unwrap_or_else(|e| e.exit());
+            let session = Session::new(constructor, rng)
+                .with_high_scores(high_scores, Category::new(entry.name, args));
+            (Box::new(session), entry.run_config)
         }
-        AppName::Particles => {
-            let (app, run_config) = Particles::new();
-            (Box::new(app), run_config)
+        None => {
+            let launcher = Launcher::new(apps::APPS, rng, high_scores);
+            (Box::new(launcher), launcher::RUN_CONFIG)
         }
-        AppName::Race => {
-            let (app, run_config) = Race::new();
-            (Box::new(app), run_config)
+    };
+
+    let app: Box<dyn App> = if let Some(replay) = replay {
+        Box::new(Replayer::new(app, replay.events))
+    } else if let Some(path) = &args.record {
+        let app_args = app_args
+            .as_ref()
+            .map(|(entry, args)| (entry.name, &args[..]));
+        match Recorder::new(app, path, app_args, seed) {
+            Ok(recorder) => Box::new(recorder),
+            Err(e) => {
+                eprintln!("Couldn't record to {}: {}", path, e);
+                std::process::exit(1);
+            }
         }
+    } else {
+        app
     };
 
     let frame_rate = run_config.frame_rate;
@@ -96,140 +172,40 @@ fn main() {
             terminal::run_main_loop(app, frame_rate, cell_width)
         }
         Runtime::Debug => debug::run_main_loop(app),
-    }
-}
-
-pub type Point = (i16, i16);
-
-#[derive(Debug, Copy, Clone)]
-pub enum Direction {
-    Up,
-    Left,
-    Down,
-    Right,
-}
-
-pub type Color = (u8, u8, u8);
-
-pub fn translated(point: Point, direction: Direction) -> Point {
-    let (dx, dy) = match direction {
-        Direction::Up => (0, -1),
-        Direction::Left => (-1, 0),
-        Direction::Down => (0, 1),
-        Direction::Right => (1, 0),
-    };
-    (point.0 + dx, point.1 + dy)
-}
-
-pub struct Graphics {
-    pub title: String,
-    side_panel: Option<SidePanel>,
-    pub buf: GraphicsBuf,
-}
-
-impl Graphics {
-    pub fn new(title: String, side_panel: Option<SidePanel>, graphics: GraphicsBuf) -> Self {
-        Self {
-            title,
-            side_panel,
-            buf: graphics,
-        }
-    }
-
-    pub fn side_panel(&self) -> Option<&SidePanel> {
-        self.side_panel.as_ref()
-    }
-}
-
-#[derive(Debug)]
-pub struct SidePanel {
-    pub items: Vec<PanelItem>,
-}
-
-impl SidePanel {
-    pub fn unwrap_graphics_item_mut(&mut self, index: usize) -> &mut GraphicsBuf {
-        match &mut self.items[index] {
-            PanelItem::TextItem { .. } => panic!("Expected graphics"),
-            PanelItem::GraphicsItem { buf } => buf,
-        }
-    }
-
-    pub fn unwrap_text_item_mut(&mut self, index: usize) -> &mut String {
-        match &mut self.items[index] {
-            PanelItem::TextItem { text } => text,
-            PanelItem::GraphicsItem { .. } => panic!("Expected text"),
+        Runtime::Headless => {
+            let config = HeadlessConfig {
+                frames: args.frames,
+                dump_every: args.dump_every,
+            };
+            match args.output {
+                Some(path) => {
+                    let mut file = File::create(&path).unwrap_or_else(|e| {
+                        eprintln!("Couldn't write to {}: {}", path, e);
+                        std::process::exit(1);
+                    });
+                    headless::run_main_loop(app, config, &mut file);
+                }
+                None => headless::run_main_loop(app, config, &mut std::io::stdout()),
+            }
         }
     }
 }
 
-#[derive(Debug)]
-pub enum PanelItem {
-    TextItem { text: String },
-    GraphicsItem { buf: GraphicsBuf },
-}
-
-#[derive(Debug)]
-pub struct GraphicsBuf {
-    buf: Vec<Cell>,
-    dimensions: (u8, u8),
-}
-
-impl GraphicsBuf {
-    pub fn new(dimensions: (u8, u8)) -> Self {
-        Self {
-            buf: vec![Default::default(); dimensions.0 as usize * dimensions.1 as usize],
-            dimensions,
-        }
-    }
-
-    pub fn dimensions(&self) -> (u8, u8) {
-        self.dimensions
-    }
-
-    pub fn set(&mut self, point: Point, value: Cell) {
-        let i = self.buf_index(point).unwrap();
-        self.buf[i] = value;
-    }
-
-    pub fn set_by_index(&mut self, index: usize, value: Cell) {
-        self.buf[index] = value;
-    }
-
-    pub fn get(&self, point: (i16, i16)) -> Option<Cell> {
-        self.buf_index(point).map(|i| self.buf[i])
-    }
-
-    pub fn get_by_index(&self, index: usize) -> Cell {
-        self.buf[index]
+fn print_high_scores(high_scores: &HighScores, matches: &ArgMatches) {
+    let app = matches.get_one::<String>("app");
+    let categories: Vec<&Category> = high_scores
+        .categories()
+        .into_iter()
+        .filter(|category| app.is_none_or(|app| &category.app == app))
+        .collect();
+    if categories.is_empty() {
+        println!("No scores yet");
     }
-
-    fn buf_index(&self, pos: (i16, i16)) -> Option<usize> {
-        if pos.0 >= 0
-            && pos.1 >= 0
-            && pos.0 < self.dimensions.0 as i16
-            && pos.1 < self.dimensions.1 as i16
-        {
-            Some((pos.1 * self.dimensions.0 as i16 + pos.0) as usize)
-        } else {
-            None
+    for (i, category) in categories.into_iter().enumerate() {
+        if i > 0 {
+            println!();
         }
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum Cell {
-    Blank,
-    Colored(Color),
-}
-
-impl Default for Cell {
-    fn default() -> Self {
-        Self::Blank
-    }
-}
-
-impl Cell {
-    pub fn filled() -> Self {
-        Self::Colored((255, 255, 255))
+        println!("{}", category);
+        println!("{}", high_scores.format_table(category, None));
     }
 }
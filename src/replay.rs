@@ -0,0 +1,308 @@
+use crate::apps::{self, App, AppStatus};
+use crate::input::{self, KeyCode, KeyEvent, KeyEventKind};
+use crate::Graphics;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// An input event along with the frame it was handled at.
+#[derive(Debug, Copy, Clone)]
+pub struct ScriptedEvent {
+    /// The event is handled right before this frame is run
+    pub frame: u32,
+    pub event: KeyEvent,
+}
+
+/// A recorded session.
+///
+/// The app is named along with any options that were given to it. If no app is named, the
+/// one given on the command line is used, or else the launcher menu.
+///
+/// The file format is line based. An optional header names the app and the seed, and is
+/// followed by one event per line on the form "<frame> [press|repeat|release] <key>", for
+/// example "12 press space". If the kind is left out, a press is immediately followed by
+/// a release. Empty lines and lines starting with '#' are ignored.
+///
+/// ```text
+/// app snake --width 40
+/// seed 1234
+/// 0 press d
+/// 0 release d
+/// ```
+pub struct Replay {
+    /// The app's name and its options
+    pub app: Option<(String, Vec<String>)>,
+    pub seed: Option<u64>,
+    pub events: Vec<ScriptedEvent>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Couldn't open {}: {}", path, e))?;
+        let mut replay = Replay {
+            app: None,
+            seed: None,
+            events: vec![],
+        };
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            replay
+                .parse_line(line.trim())
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        }
+        // Events are stable-sorted so that the order within a frame is preserved
+        replay.events.sort_by_key(|scripted| scripted.frame);
+        Ok(replay)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        // The app's options can contain spaces, within quotes
+        if let Some(app) = line
+            .strip_prefix("app")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+        {
+            let mut words = apps::split_args(app)?.into_iter();
+            let name = words.next().ok_or("Missing the app's name")?;
+            self.app = Some((name, words.collect()));
+            return Ok(());
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => {}
+            [first, ..] if first.starts_with('#') => {}
+            ["seed", seed] => {
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {:?}", seed))?;
+                self.seed = Some(seed);
+            }
+            [frame, key] => {
+                let frame = parse_frame(frame)?;
+                let (code, modifiers) = input::parse_key(key)?;
+                for kind in [KeyEventKind::Press, KeyEventKind::Release] {
+                    self.events.push(ScriptedEvent {
+                        frame,
+                        event: KeyEvent::new(code, modifiers, kind),
+                    });
+                }
+            }
+            [frame, kind, key] => {
+                let frame = parse_frame(frame)?;
+                let kind = match kind {
+                    "press" => KeyEventKind::Press,
+                    "repeat" => KeyEventKind::Repeat,
+                    "release" => KeyEventKind::Release,
+                    unknown => return Err(format!("Unknown event kind: {:?}", unknown)),
+                };
+                let (code, modifiers) = input::parse_key(key)?;
+                self.events.push(ScriptedEvent {
+                    frame,
+                    event: KeyEvent::new(code, modifiers, kind),
+                });
+            }
+            _ => return Err(format!("Expected '<frame> [kind] <key>', got {:?}", line)),
+        }
+        Ok(())
+    }
+}
+
+fn parse_frame(frame: &str) -> Result<u32, String> {
+    frame
+        .parse()
+        .map_err(|_| format!("Invalid frame: {:?}", frame))
+}
+
+fn format_app(name: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        format!("app {}", name)
+    } else {
+        format!("app {} {}", name, apps::join_args(args))
+    }
+}
+
+fn format_event(scripted: &ScriptedEvent) -> String {
+    let event = scripted.event;
+    let kind = match event.kind {
+        KeyEventKind::Press => "press",
+        KeyEventKind::Repeat => "repeat",
+        KeyEventKind::Release => "release",
+    };
+    let key = input::format_key(event.code, event.modifiers);
+    format!("{} {} {}", scripted.frame, kind, key)
+}
+
+/// Wraps an app and writes every input event it receives to a replay file.
+pub struct Recorder {
+    app: Box<dyn App>,
+    frame: u32,
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn new(
+        app: Box<dyn App>,
+        path: &str,
+        app_args: Option<(&str, &[String])>,
+        seed: u64,
+    ) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if let Some((app_name, args)) = app_args {
+            writeln!(writer, "{}", format_app(app_name, args))?;
+        }
+        writeln!(writer, "seed {}", seed)?;
+        writer.flush()?;
+        Ok(Self {
+            app,
+            frame: 0,
+            writer,
+        })
+    }
+}
+
+impl App for Recorder {
+    fn run_frame(&mut self) {
+        self.app.run_frame();
+        self.frame += 1;
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        let scripted = ScriptedEvent {
+            frame: self.frame,
+            event,
+        };
+        // Flushing on every event, since some runtimes exit the process without
+        // giving us a chance to clean up.
+        writeln!(self.writer, "{}", format_event(&scripted)).unwrap();
+        self.writer.flush().unwrap();
+
+        self.app.handle_key_event(event);
+    }
+
+    fn graphics(&self) -> &Graphics {
+        self.app.graphics()
+    }
+
+    fn status(&self) -> AppStatus {
+        self.app.status()
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.app.frame_rate()
+    }
+}
+
+/// Wraps an app and feeds it previously recorded events at the same frames they were
+/// originally handled at. Any live input is ignored, except for quitting.
+pub struct Replayer {
+    app: Box<dyn App>,
+    frame: u32,
+    events: VecDeque<ScriptedEvent>,
+    quit_requested: bool,
+}
+
+impl Replayer {
+    pub fn new(app: Box<dyn App>, events: Vec<ScriptedEvent>) -> Self {
+        Self {
+            app,
+            frame: 0,
+            events: events.into(),
+            quit_requested: false,
+        }
+    }
+}
+
+impl App for Replayer {
+    fn run_frame(&mut self) {
+        while let Some(scripted) = self.events.front() {
+            if scripted.frame > self.frame {
+                break;
+            }
+            self.app.handle_key_event(scripted.event);
+            self.events.pop_front();
+        }
+
+        self.app.run_frame();
+        self.frame += 1;
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if let KeyCode::Char('q') | KeyCode::Escape = event.code {
+            self.quit_requested = true;
+        }
+    }
+
+    fn graphics(&self) -> &Graphics {
+        self.app.graphics()
+    }
+
+    fn status(&self) -> AppStatus {
+        if self.quit_requested {
+            AppStatus::RequestQuit
+        } else {
+            self.app.status()
+        }
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.app.frame_rate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Modifiers;
+
+    fn empty_replay() -> Replay {
+        Replay {
+            app: None,
+            seed: None,
+            events: vec![],
+        }
+    }
+
+    #[test]
+    fn apps_can_be_parsed_back() {
+        let args = vec![
+            "--level".to_string(),
+            "my level.txt".to_string(),
+            "--name".to_string(),
+            "say \"hi\"".to_string(),
+        ];
+        let mut replay = empty_replay();
+        replay.parse_line(&format_app("snake", &args)).unwrap();
+        assert_eq!(replay.app, Some(("snake".to_string(), args)));
+
+        let mut replay = empty_replay();
+        replay.parse_line(&format_app("tetris", &[])).unwrap();
+        assert_eq!(replay.app, Some(("tetris".to_string(), vec![])));
+    }
+
+    #[test]
+    fn events_can_be_parsed_back() {
+        let mut replay = empty_replay();
+        let mut expected = vec![];
+        for kind in [
+            KeyEventKind::Press,
+            KeyEventKind::Repeat,
+            KeyEventKind::Release,
+        ] {
+            for code in [KeyCode::Char('+'), KeyCode::Char('x'), KeyCode::Enter] {
+                let modifiers = Modifiers {
+                    ctrl: true,
+                    ..Default::default()
+                };
+                let scripted = ScriptedEvent {
+                    frame: 7,
+                    event: KeyEvent::new(code, modifiers, kind),
+                };
+                replay.parse_line(&format_event(&scripted)).unwrap();
+                expected.push(scripted.event);
+            }
+        }
+        let events: Vec<KeyEvent> = replay.events.iter().map(|s| s.event).collect();
+        assert_eq!(events, expected);
+        assert!(replay.events.iter().all(|s| s.frame == 7));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::apps;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many scores are kept for each category
+pub const TABLE_SIZE: usize = 10;
+
+/// The longest name that can be entered along with a score
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// Scores are only compared against others of the same app, played with the same options.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Category {
+    pub app: String,
+    /// The options the app was played with, quoted as by [apps::join_args], or "default"
+    pub mode: String,
+}
+
+impl Category {
+    pub fn new(app: &str, args: &[String]) -> Self {
+        let mode = if args.is_empty() {
+            "default".to_string()
+        } else {
+            apps::join_args(args)
+        };
+        Self {
+            app: app.to_string(),
+            mode,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.mode == "default" {
+            write!(f, "{}", self.app)
+        } else {
+            write!(f, "{} ({})", self.app, self.mode)
+        }
+    }
+}
+
+/// What an app's score measures, which decides how scores are ranked and shown
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScoreKind {
+    /// Points, where more is better
+    Points,
+    /// A time in milliseconds, where less is better
+    Time,
+}
+
+impl ScoreKind {
+    pub fn format(&self, score: u32) -> String {
+        match self {
+            ScoreKind::Points => score.to_string(),
+            ScoreKind::Time => format_time(score),
+        }
+    }
+
+    fn is_better(&self, score: u32, than: u32) -> bool {
+        match self {
+            ScoreKind::Points => score > than,
+            ScoreKind::Time => score < than,
+        }
+    }
+}
+
+/// Formats milliseconds as e.g. "1:05.20"
+pub fn format_time(millis: u32) -> String {
+    let centis = millis / 10;
+    format!(
+        "{}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+fn parse_time(text: &str) -> Option<u32> {
+    let (minutes, seconds) = text.split_once(':')?;
+    let (seconds, centis) = seconds.split_once('.')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let seconds: u32 = seconds.parse().ok()?;
+    let centis: u32 = centis.parse().ok()?;
+    Some(((minutes * 60 + seconds) * 100 + centis) * 10)
+}
+
+#[derive(Debug, Clone)]
+pub struct ScoreEntry {
+    pub category: Category,
+    pub name: String,
+    pub score: u32,
+    pub kind: ScoreKind,
+}
+
+/// The best scores of every app, optionally kept in a file between runs.
+///
+/// The file has one score per line, with the app, mode, score and name separated by tabs.
+/// Times are written like "1:05.20", and all other scores as plain numbers. Empty lines and
+/// lines starting with '#' are ignored.
+pub struct HighScores {
+    path: Option<PathBuf>,
+    // In the order they were added
+    entries: Vec<ScoreEntry>,
+}
+
+impl HighScores {
+    /// Where the scores are kept unless told otherwise: "terminal-games/scores.txt" in the
+    /// user's data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        let data_dir = var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+            .or_else(|| var("APPDATA").map(PathBuf::from))?;
+        Some(data_dir.join("terminal-games").join("scores.txt"))
+    }
+
+    /// Scores that are forgotten when the process exits
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: vec![],
+        }
+    }
+
+    /// Loads the scores from a file, which doesn't have to exist yet. New scores are saved
+    /// back to it.
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let mut entries = vec![];
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Couldn't read {}: {}", path.display(), e)),
+        };
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = parse_entry(line)
+                .ok_or_else(|| format!("{}: invalid line {}", path.display(), i + 1))?;
+            entries.push(entry);
+        }
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    /// The best scores of a category, best first. Among equal scores, the oldest comes first.
+    pub fn top(&self, category: &Category) -> Vec<&ScoreEntry> {
+        self.ranked(category)
+            .into_iter()
+            .take(TABLE_SIZE)
+            .map(|i| &self.entries[i])
+            .collect()
+    }
+
+    /// The indices of all of a category's entries, best first
+    fn ranked(&self, category: &Category) -> Vec<usize> {
+        let mut ranked: Vec<usize> = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            if &entry.category != category {
+                continue;
+            }
+            let index = ranked
+                .iter()
+                .position(|&other| entry.kind.is_better(entry.score, self.entries[other].score))
+                .unwrap_or(ranked.len());
+            ranked.insert(index, i);
+        }
+        ranked
+    }
+
+    /// All categories that have any scores, sorted by app and mode
+    pub fn categories(&self) -> Vec<&Category> {
+        let mut categories: Vec<&Category> = vec![];
+        for entry in &self.entries {
+            if !categories.contains(&&entry.category) {
+                categories.push(&entry.category);
+            }
+        }
+        categories.sort_by(|a, b| (&a.app, &a.mode).cmp(&(&b.app, &b.mode)));
+        categories
+    }
+
+    /// Adds a score and saves the table. Returns the score's rank within its category, or
+    /// None if it wasn't good enough to make it into the table.
+    pub fn add(
+        &mut self,
+        category: Category,
+        name: &str,
+        score: u32,
+        kind: ScoreKind,
+    ) -> Result<Option<usize>, String> {
+        self.entries.push(ScoreEntry {
+            category: category.clone(),
+            name: name.to_string(),
+            score,
+            kind,
+        });
+        let added = self.entries.len() - 1;
+
+        let ranked = self.ranked(&category);
+        let rank = ranked.iter().position(|&i| i == added).unwrap();
+        // Forget the scores that fell out of the table
+        let dropped = &ranked[ranked.len().min(TABLE_SIZE)..];
+        let mut i = 0;
+        self.entries.retain(|_| {
+            i += 1;
+            !dropped.contains(&(i - 1))
+        });
+
+        self.save()?;
+        Ok(if rank < TABLE_SIZE { Some(rank) } else { None })
+    }
+
+    /// The table of a category as text, one score per line. The score at `highlight` is
+    /// marked with an arrow.
+    pub fn format_table(&self, category: &Category, highlight: Option<usize>) -> String {
+        let top = self.top(category);
+        if top.is_empty() {
+            return "No scores yet".to_string();
+        }
+        top.iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let marker = if Some(i) == highlight { ">" } else { " " };
+                format!(
+                    "{}{:>2}. {:<width$} {:>6}",
+                    marker,
+                    i + 1,
+                    entry.name,
+                    entry.kind.format(entry.score),
+                    width = MAX_NAME_LENGTH
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut content = "# app\tmode\tscore\tname\n".to_string();
+        for entry in &self.entries {
+            content.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                entry.category.app,
+                entry.category.mode,
+                entry.kind.format(entry.score),
+                entry.name
+            ));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+        }
+        fs::write(path, content).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+    }
+}
+
+fn parse_entry(line: &str) -> Option<ScoreEntry> {
+    let mut fields = line.splitn(4, '\t');
+    let app = fields.next()?;
+    let mode = fields.next()?;
+    let score = fields.next()?;
+    let (score, kind) = match parse_time(score) {
+        Some(time) => (time, ScoreKind::Time),
+        None => (score.parse().ok()?, ScoreKind::Points),
+    };
+    let name = fields.next()?;
+    Some(ScoreEntry {
+        category: Category {
+            app: app.to_string(),
+            mode: mode.to_string(),
+        },
+        name: name.to_string(),
+        score,
+        kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_can_be_loaded_back() {
+        let path = std::env::temp_dir().join(format!("scores-test-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let snake = Category::new(
+            "snake",
+            &["--level".to_string(), "my level.txt".to_string()],
+        );
+        let sprint = Category::new("tetris", &["--mode".to_string(), "sprint".to_string()]);
+        let scores = [
+            (&snake, "ann", 120, ScoreKind::Points),
+            (&snake, "bo b", 340, ScoreKind::Points),
+            (&sprint, "cy", 65_200, ScoreKind::Time),
+            (&sprint, "di", 3_723_450, ScoreKind::Time),
+        ];
+
+        let mut high_scores = HighScores::load(path.clone()).unwrap();
+        for &(category, name, score, kind) in &scores {
+            high_scores
+                .add(category.clone(), name, score, kind)
+                .unwrap();
+        }
+        let loaded = HighScores::load(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let categories: Vec<&Category> = loaded.categories();
+        assert_eq!(categories, high_scores.categories());
+        for category in categories {
+            let expected: Vec<_> = high_scores
+                .top(category)
+                .iter()
+                .map(|entry| (&entry.name, entry.score, entry.kind))
+                .collect();
+            let actual: Vec<_> = loaded
+                .top(category)
+                .iter()
+                .map(|entry| (&entry.name, entry.score, entry.kind))
+                .collect();
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(loaded.top(&snake)[0].name, "bo b");
+        assert_eq!(loaded.top(&sprint)[0].score, 65_200);
+    }
+}
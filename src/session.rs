@@ -0,0 +1,253 @@
+use crate::apps::{App, AppContext, AppStatus};
+use crate::input::{KeyCode, KeyEvent};
+use crate::scores::{self, Category, HighScores, ScoreKind};
+use crate::{Graphics, PanelItem, SidePanel};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub type AppConstructor = Rc<dyn Fn(AppContext) -> Box<dyn App>>;
+
+/// Wraps an app and gives it a uniform lifecycle, regardless of which runtime it's played in:
+/// Q or Escape quits, and once the game is over, R restarts it from scratch.
+///
+/// If the session keeps high scores, the player is asked for their name when a game ends
+/// with a score, and the table of best scores is shown.
+pub struct Session {
+    constructor: AppConstructor,
+    rng: StdRng,
+    app: Box<dyn App>,
+    quit_requested: bool,
+    high_scores: Option<(Rc<RefCell<HighScores>>, Category)>,
+    score_entry: ScoreEntry,
+    // The app's graphics, with the session's own status appended to the side panel
+    graphics: Graphics,
+}
+
+impl Session {
+    pub fn new(constructor: AppConstructor, mut rng: StdRng) -> Self {
+        let app = constructor(Self::app_context(&mut rng));
+        let graphics = app.graphics().clone();
+        let mut this = Self {
+            constructor,
+            rng,
+            app,
+            quit_requested: false,
+            high_scores: None,
+            score_entry: ScoreEntry::Playing,
+            graphics,
+        };
+        this.update_graphics();
+        this
+    }
+
+    /// Keeps the scores of finished games in the given table, under the given category
+    pub fn with_high_scores(
+        mut self,
+        high_scores: Rc<RefCell<HighScores>>,
+        category: Category,
+    ) -> Self {
+        self.high_scores = Some((high_scores, category));
+        self.update_graphics();
+        self
+    }
+
+    fn app_context(rng: &mut StdRng) -> AppContext {
+        // Every (re)started app gets its own RNG, derived from the session's. That way
+        // restarted games differ from each other, while the session stays reproducible.
+        AppContext {
+            rng: StdRng::from_rng(rng).unwrap(),
+        }
+    }
+
+    fn restart(&mut self) {
+        self.app = (self.constructor)(Self::app_context(&mut self.rng));
+        self.score_entry = ScoreEntry::Playing;
+    }
+
+    /// Starts asking for the player's name once the game is over
+    fn check_game_over(&mut self) {
+        if !matches!(self.score_entry, ScoreEntry::Playing) || self.high_scores.is_none() {
+            return;
+        }
+        match self.app.status().final_score() {
+            Some(score) if score > 0 => {
+                self.score_entry = ScoreEntry::EnteringName {
+                    score,
+                    name: String::new(),
+                };
+            }
+            Some(_) => {
+                self.score_entry = ScoreEntry::Done {
+                    rank: None,
+                    error: None,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_name_key_event(&mut self, event: KeyEvent) {
+        let ScoreEntry::EnteringName { score, name } = &mut self.score_entry else {
+            return;
+        };
+        if !event.is_press() {
+            return;
+        }
+        match event.code {
+            KeyCode::Char(c) if name.chars().count() < scores::MAX_NAME_LENGTH => {
+                if event.modifiers.shift {
+                    name.extend(c.to_uppercase());
+                } else {
+                    name.push(c);
+                }
+            }
+            KeyCode::Space
+                if !name.is_empty() && name.chars().count() < scores::MAX_NAME_LENGTH =>
+            {
+                name.push(' ');
+            }
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Enter => {
+                let name = match name.trim() {
+                    "" => "anonymous",
+                    name => name,
+                };
+                let (high_scores, category) = self.high_scores.as_ref().unwrap();
+                let result = high_scores.borrow_mut().add(
+                    category.clone(),
+                    name,
+                    *score,
+                    self.app.score_kind(),
+                );
+                self.score_entry = match result {
+                    Ok(rank) => ScoreEntry::Done { rank, error: None },
+                    Err(error) => ScoreEntry::Done {
+                        rank: None,
+                        error: Some(error),
+                    },
+                };
+            }
+            KeyCode::Escape => {
+                self.score_entry = ScoreEntry::Done {
+                    rank: None,
+                    error: None,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn update_graphics(&mut self) {
+        self.graphics.clone_from(self.app.graphics());
+
+        let status = self.app.status();
+        let Some(score) = status.final_score() else {
+            return;
+        };
+        // An app that was won says so itself, so there's no need for a prefix
+        let prefix = match status {
+            AppStatus::Won { .. } => "",
+            _ => "Game over! ",
+        };
+        let result = match (self.app.score_kind(), score) {
+            (ScoreKind::Points, score) => format!("{}Score: {}", prefix, score),
+            (ScoreKind::Time, 0) => prefix.trim_end().to_string(),
+            (ScoreKind::Time, time) => format!("{}Time: {}", prefix, scores::format_time(time)),
+        };
+        let mut items = vec![];
+        match &self.score_entry {
+            ScoreEntry::EnteringName { name, .. } => {
+                items.push(format!(
+                    "{}\nEnter your name: {}_\nEnter: save, Esc: skip",
+                    result, name
+                ));
+            }
+            ScoreEntry::Playing | ScoreEntry::Done { .. } => {
+                items.push(format!("{}\nPress R to restart, Q to quit", result));
+            }
+        }
+        if let (Some((high_scores, category)), ScoreEntry::Done { rank, error }) =
+            (&self.high_scores, &self.score_entry)
+        {
+            if let Some(error) = error {
+                items.push(format!("Couldn't save the score: {}", error));
+            }
+            let table = high_scores.borrow().format_table(category, *rank);
+            items.push(format!("High scores:\n{}", table));
+        }
+
+        let side_panel = self
+            .graphics
+            .side_panel
+            .get_or_insert_with(|| SidePanel { items: vec![] });
+        for text in items {
+            side_panel.items.push(PanelItem::TextItem { text });
+        }
+    }
+}
+
+impl App for Session {
+    fn run_frame(&mut self) {
+        self.app.run_frame();
+        self.check_game_over();
+        self.update_graphics();
+    }
+
+    fn handle_key_event(&mut self, event: KeyEvent) {
+        if matches!(self.score_entry, ScoreEntry::EnteringName { .. }) {
+            self.handle_name_key_event(event);
+            self.update_graphics();
+            return;
+        }
+        match event.code {
+            KeyCode::Char('q') | KeyCode::Escape => {
+                if event.is_press() {
+                    self.quit_requested = true;
+                }
+            }
+            KeyCode::Char('r') if self.app.status().final_score().is_some() => {
+                if event.is_press() {
+                    self.restart();
+                }
+            }
+            _ => self.app.handle_key_event(event),
+        }
+        self.check_game_over();
+        self.update_graphics();
+    }
+
+    fn graphics(&self) -> &Graphics {
+        &self.graphics
+    }
+
+    fn status(&self) -> AppStatus {
+        if self.quit_requested {
+            AppStatus::RequestQuit
+        } else {
+            self.app.status()
+        }
+    }
+
+    fn frame_rate(&self) -> Option<u32> {
+        self.app.frame_rate()
+    }
+}
+
+/// Where a game is at with regards to the high scores
+enum ScoreEntry {
+    Playing,
+    EnteringName {
+        score: u32,
+        name: String,
+    },
+    /// The score was either entered, skipped, or not worth entering
+    Done {
+        /// The score's place in the table, if it made it there
+        rank: Option<usize>,
+        error: Option<String>,
+    },
+}
@@ -1,3 +1,4 @@
 pub mod debug;
+pub mod headless;
 pub mod terminal;
 pub mod window;
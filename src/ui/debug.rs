@@ -1,3 +1,5 @@
+use crate::apps::AppStatus;
+use crate::input::{self, KeyEvent, KeyEventKind};
 use crate::{App, Cell, GraphicsBuf};
 use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor};
 use std::io::Write;
@@ -16,16 +18,28 @@ pub fn run_main_loop(mut app: Box<dyn App>) {
         stdout.flush().unwrap();
         input.clear();
 
-        stdin.read_line(&mut input).unwrap();
-        if let Some(ch) = input.chars().next() {
-            if ch == 'q' {
-                println!("Good bye.");
-                break;
+        if stdin.read_line(&mut input).unwrap() == 0 {
+            // Reached the end of a piped input
+            break;
+        }
+        if !input.trim().is_empty() {
+            // Keys are entered as e.g. "a", "space" or "ctrl+left"
+            match input::parse_key(&input) {
+                Ok((code, modifiers)) => {
+                    app.handle_key_event(KeyEvent::new(code, modifiers, KeyEventKind::Press));
+                    app.handle_key_event(KeyEvent::new(code, modifiers, KeyEventKind::Release));
+                }
+                Err(e) => println!("{}", e),
             }
-            app.handle_pressed_key(ch);
         }
 
-        app.run_frame();
+        if app.status() != AppStatus::RequestQuit {
+            app.run_frame();
+        }
+        if app.status() == AppStatus::RequestQuit {
+            println!("Good bye.");
+            break;
+        }
     }
 }
 
@@ -0,0 +1,60 @@
+use crate::apps::AppStatus;
+use crate::{App, Cell, Graphics, GraphicsBuf, PanelItem};
+use std::io::Write;
+
+pub struct HeadlessConfig {
+    /// How many frames to run before exiting
+    pub frames: u32,
+    /// Dump the graphics every k frames. If None, only the final frame is dumped.
+    pub dump_every: Option<u32>,
+}
+
+/// Runs the app as fast as possible, without any user input. Scripted input can be fed to
+/// the app by wrapping it in a [crate::replay::Replayer].
+pub fn run_main_loop(mut app: Box<dyn App>, config: HeadlessConfig, output: &mut dyn Write) {
+    for frame in 0..config.frames {
+        app.run_frame();
+
+        let is_last = frame + 1 == config.frames;
+        let should_dump = match config.dump_every {
+            Some(k) => (frame + 1).is_multiple_of(k) || is_last,
+            None => is_last,
+        };
+        if should_dump {
+            dump(output, frame + 1, app.graphics()).unwrap();
+        }
+        if app.status() == AppStatus::RequestQuit {
+            if !should_dump {
+                dump(output, frame + 1, app.graphics()).unwrap();
+            }
+            break;
+        }
+    }
+}
+
+fn dump(output: &mut dyn Write, frame: u32, graphics: &Graphics) -> std::io::Result<()> {
+    writeln!(output, "=== Frame {}: {} ===", frame, graphics.title)?;
+    dump_buf(output, &graphics.buf)?;
+    if let Some(panel) = graphics.side_panel() {
+        for item in &panel.items {
+            match item {
+                PanelItem::TextItem { text } => writeln!(output, "{}", text)?,
+                PanelItem::GraphicsItem { buf } => dump_buf(output, buf)?,
+            }
+        }
+    }
+    output.flush()
+}
+
+fn dump_buf(output: &mut dyn Write, buf: &GraphicsBuf) -> std::io::Result<()> {
+    for y in 0..buf.dimensions().1 {
+        let row: String = (0..buf.dimensions().0)
+            .map(|x| match buf.get((x as i16, y as i16)).unwrap() {
+                Cell::Blank => '.',
+                Cell::Colored(_) => '#',
+            })
+            .collect();
+        writeln!(output, "{}", row)?;
+    }
+    Ok(())
+}
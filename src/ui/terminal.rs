@@ -5,8 +5,10 @@ use tui::backend::CrosstermBackend;
 use tui::widgets::{Block, BorderType, Borders, Paragraph, Widget};
 use tui::Terminal;
 
+use crate::apps::AppStatus;
+use crate::input::{KeyCode, KeyEvent, KeyEventKind, Modifiers};
 use crate::{App, Cell, Graphics, GraphicsBuf, PanelItem};
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::event::{self, Event, KeyModifiers};
 use std::cmp::{max, min};
 use std::time::{Duration, Instant};
 use tui::buffer::Buffer;
@@ -20,8 +22,9 @@ pub fn run_main_loop(mut app: Box<dyn App>, frame_rate: u32, cell_width: u16) {
 
     let mut previous_update = Instant::now();
 
-    let frame_duration = Duration::from_millis((1000 / frame_rate) as u64);
     loop {
+        let frame_rate = app.frame_rate().unwrap_or(frame_rate);
+        let frame_duration = Duration::from_millis((1000 / frame_rate) as u64);
         while let Some(until_next) =
             frame_duration.checked_sub(Instant::now().duration_since(previous_update))
         {
@@ -30,20 +33,26 @@ pub fn run_main_loop(mut app: Box<dyn App>, frame_rate: u32, cell_width: u16) {
                     InputEvent::Quit => {
                         return;
                     }
-                    InputEvent::KeyPressed(char) => {
-                        app.handle_pressed_key(char);
+                    InputEvent::KeyPressed(code, modifiers) => {
+                        app.handle_key_event(KeyEvent::new(code, modifiers, KeyEventKind::Press));
                         // Terminals generally don't emit release events, so
                         // we have to rely on the builtin "repeated key press" event
                         // instead of signalling the accurate duration of a key press
                         // to the app.
-                        app.handle_released_key(char);
+                        app.handle_key_event(KeyEvent::new(code, modifiers, KeyEventKind::Release));
                     }
                 }
+                if app.status() == AppStatus::RequestQuit {
+                    return;
+                }
             }
         }
 
         app.run_frame();
         previous_update = Instant::now();
+        if app.status() == AppStatus::RequestQuit {
+            return;
+        }
 
         ui.render(app.graphics());
     }
@@ -109,9 +118,6 @@ impl TerminalUi {
                     .border_type(BorderType::Double);
                 let header_container_rect = container_sub_rects[0];
 
-                let mut header_rect = header_container_rect;
-                header_rect.height = min(header_rect.height, 1);
-
                 let content = BufWidget {
                     app_buf: &graphics.buf,
                     cell_width: self.cell_width,
@@ -188,27 +194,22 @@ impl TerminalUi {
             let event = crossterm::event::read().unwrap();
             if let Event::Key(key_event) = event {
                 match key_event {
-                    KeyEvent {
-                        code: KeyCode::Char('q'),
-                        ..
-                    } => {
-                        return Some(InputEvent::Quit);
-                    }
-                    KeyEvent {
-                        code: KeyCode::Char('c'),
+                    crossterm::event::KeyEvent {
+                        code: event::KeyCode::Char('c'),
                         modifiers: KeyModifiers::CONTROL,
-                        kind: KeyEventKind::Press,
+                        kind: event::KeyEventKind::Press,
                         ..
                     } => {
                         return Some(InputEvent::Quit);
                     }
-                    KeyEvent {
-                        code: KeyCode::Char(ch),
-                        kind,
+                    crossterm::event::KeyEvent {
+                        code,
+                        modifiers,
+                        kind: event::KeyEventKind::Press,
                         ..
                     } => {
-                        if kind == KeyEventKind::Press {
-                            return Some(InputEvent::KeyPressed(ch));
+                        if let Some((code, modifiers)) = translate_key(code, modifiers) {
+                            return Some(InputEvent::KeyPressed(code, modifiers));
                         }
                     }
                     _ => {}
@@ -261,7 +262,38 @@ impl Drop for TerminalUi {
 
 enum InputEvent {
     Quit,
-    KeyPressed(char),
+    KeyPressed(KeyCode, Modifiers),
+}
+
+fn translate_key(code: event::KeyCode, modifiers: KeyModifiers) -> Option<(KeyCode, Modifiers)> {
+    let mut modifiers = Modifiers {
+        shift: modifiers.contains(KeyModifiers::SHIFT),
+        ctrl: modifiers.contains(KeyModifiers::CONTROL),
+        alt: modifiers.contains(KeyModifiers::ALT),
+    };
+    let code = match code {
+        event::KeyCode::Char(' ') => KeyCode::Space,
+        event::KeyCode::Char(ch) if ch.is_uppercase() => {
+            modifiers.shift = true;
+            KeyCode::Char(ch.to_ascii_lowercase())
+        }
+        event::KeyCode::Char(ch) => KeyCode::Char(ch),
+        event::KeyCode::Up => KeyCode::Up,
+        event::KeyCode::Down => KeyCode::Down,
+        event::KeyCode::Left => KeyCode::Left,
+        event::KeyCode::Right => KeyCode::Right,
+        event::KeyCode::Enter => KeyCode::Enter,
+        event::KeyCode::Esc => KeyCode::Escape,
+        event::KeyCode::Tab => KeyCode::Tab,
+        // Shift+Tab is reported as a separate key code
+        event::KeyCode::BackTab => {
+            modifiers.shift = true;
+            KeyCode::Tab
+        }
+        event::KeyCode::Backspace => KeyCode::Backspace,
+        _ => return None,
+    };
+    Some((code, modifiers))
 }
 
 fn claim_terminal(stdout: Stdout) -> Terminal<CrosstermBackend<Stdout>> {
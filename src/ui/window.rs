@@ -1,29 +1,21 @@
 use ggez::conf::{WindowMode, WindowSetup};
 use ggez::event::{self, EventHandler};
 use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Mesh, Quad, Rect, Text};
-use ggez::input::keyboard::{KeyCode, KeyInput};
+use ggez::input::keyboard::{KeyCode as VirtualKeyCode, KeyInput, KeyMods};
 use ggez::{Context, ContextBuilder, GameResult};
 
+use crate::apps::AppStatus;
+use crate::input::{KeyCode, KeyEvent, KeyEventKind, Modifiers};
 use crate::{App, PanelItem};
-use crate::{Cell, GraphicsBuf};
+use crate::{Cell, Graphics, GraphicsBuf};
 
 const GRAPHICS_MARGIN: f32 = 10.0;
 const CELL_SIZE: f32 = 30.0;
 const TEXT_AREA_WIDTH: f32 = 300.0;
 
 pub fn run_main_loop(app: Box<dyn App>, frame_rate: u32) -> ! {
-    let side_panel = app.graphics().side_panel();
     let title = app.graphics().title.to_string();
-    let (w, h) = app.graphics().buf.dimensions();
-
-    let window_w = GRAPHICS_MARGIN * 2.0
-        + CELL_SIZE * w as f32
-        + if side_panel.is_some() {
-            TEXT_AREA_WIDTH
-        } else {
-            0.0
-        };
-    let window_h = GRAPHICS_MARGIN * 2.0 + CELL_SIZE * h as f32;
+    let (window_w, window_h) = window_size(app.graphics());
 
     let (ctx, event_loop) = ContextBuilder::new("ggez_ui", "some_author")
         .window_setup(WindowSetup::default().title(&title))
@@ -36,6 +28,7 @@ pub fn run_main_loop(app: Box<dyn App>, frame_rate: u32) -> ! {
         scaling: CELL_SIZE,
         frame_rate,
         title,
+        window_size: (window_w, window_h),
     };
 
     event::run(ctx, event_loop, event_handler);
@@ -46,14 +39,33 @@ struct AppEventHandler {
     scaling: f32,
     frame_rate: u32,
     title: String,
+    window_size: (f32, f32),
+}
+
+fn window_size(graphics: &Graphics) -> (f32, f32) {
+    let (w, h) = graphics.buf.dimensions();
+    let window_w = GRAPHICS_MARGIN * 2.0
+        + CELL_SIZE * w as f32
+        + if graphics.side_panel().is_some() {
+            TEXT_AREA_WIDTH
+        } else {
+            0.0
+        };
+    let window_h = GRAPHICS_MARGIN * 2.0 + CELL_SIZE * h as f32;
+    (window_w, window_h)
 }
 
 impl EventHandler for AppEventHandler {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while ctx.time.check_update_time(self.frame_rate) {
+        let frame_rate = self.app.frame_rate().unwrap_or(self.frame_rate);
+        while ctx.time.check_update_time(frame_rate) {
             self.app.run_frame();
         }
 
+        if self.app.status() == AppStatus::RequestQuit {
+            ctx.request_quit();
+        }
+
         Ok(())
     }
 
@@ -67,6 +79,13 @@ impl EventHandler for AppEventHandler {
             ctx.gfx.set_window_title(&self.title);
         }
 
+        // For example, a side panel may have appeared
+        let window_size = window_size(self.app.graphics());
+        if window_size != self.window_size {
+            self.window_size = window_size;
+            ctx.gfx.set_drawable_size(window_size.0, window_size.1)?;
+        }
+
         let graphics_width = self.scaling * buf.dimensions().0 as f32;
         let graphics_height = self.scaling * buf.dimensions().1 as f32;
 
@@ -109,23 +128,23 @@ impl EventHandler for AppEventHandler {
         canvas.finish(ctx)
     }
 
-    fn key_down_event(
-        &mut self,
-        ctx: &mut Context,
-        input: KeyInput,
-        _repeated: bool,
-    ) -> GameResult {
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
         if let KeyInput {
             keycode: Some(key), ..
         } = input
         {
-            if key == KeyCode::Q {
-                ctx.request_quit();
-                return Ok(());
-            }
-
-            if let Some(ch) = keycode_to_char(key) {
-                self.app.handle_pressed_key(ch);
+            if let Some(code) = translate_keycode(key) {
+                let kind = if repeated {
+                    KeyEventKind::Repeat
+                } else {
+                    KeyEventKind::Press
+                };
+                let modifiers = translate_mods(input.mods);
+                self.app
+                    .handle_key_event(KeyEvent::new(code, modifiers, kind));
+                if self.app.status() == AppStatus::RequestQuit {
+                    ctx.request_quit();
+                }
             }
         }
 
@@ -137,8 +156,10 @@ impl EventHandler for AppEventHandler {
             keycode: Some(key), ..
         } = input
         {
-            if let Some(ch) = keycode_to_char(key) {
-                self.app.handle_released_key(ch);
+            if let Some(code) = translate_keycode(key) {
+                let modifiers = translate_mods(input.mods);
+                self.app
+                    .handle_key_event(KeyEvent::new(code, modifiers, KeyEventKind::Release));
             }
         }
 
@@ -171,17 +192,66 @@ impl AppEventHandler {
     }
 }
 
-fn keycode_to_char(key_code: KeyCode) -> Option<char> {
-    use KeyCode::*;
-    match key_code {
-        W => Some('w'),
-        A => Some('a'),
-        S => Some('s'),
-        D => Some('d'),
-        Q => Some('q'),
+fn translate_keycode(key_code: VirtualKeyCode) -> Option<KeyCode> {
+    use VirtualKeyCode::*;
+    let code = match key_code {
+        A => KeyCode::Char('a'),
+        B => KeyCode::Char('b'),
+        C => KeyCode::Char('c'),
+        D => KeyCode::Char('d'),
+        E => KeyCode::Char('e'),
+        F => KeyCode::Char('f'),
+        G => KeyCode::Char('g'),
+        H => KeyCode::Char('h'),
+        I => KeyCode::Char('i'),
+        J => KeyCode::Char('j'),
+        K => KeyCode::Char('k'),
+        L => KeyCode::Char('l'),
+        M => KeyCode::Char('m'),
+        N => KeyCode::Char('n'),
+        O => KeyCode::Char('o'),
+        P => KeyCode::Char('p'),
+        Q => KeyCode::Char('q'),
+        R => KeyCode::Char('r'),
+        S => KeyCode::Char('s'),
+        T => KeyCode::Char('t'),
+        U => KeyCode::Char('u'),
+        V => KeyCode::Char('v'),
+        W => KeyCode::Char('w'),
+        X => KeyCode::Char('x'),
+        Y => KeyCode::Char('y'),
+        Z => KeyCode::Char('z'),
+        Key0 | Numpad0 => KeyCode::Char('0'),
+        Key1 | Numpad1 => KeyCode::Char('1'),
+        Key2 | Numpad2 => KeyCode::Char('2'),
+        Key3 | Numpad3 => KeyCode::Char('3'),
+        Key4 | Numpad4 => KeyCode::Char('4'),
+        Key5 | Numpad5 => KeyCode::Char('5'),
+        Key6 | Numpad6 => KeyCode::Char('6'),
+        Key7 | Numpad7 => KeyCode::Char('7'),
+        Key8 | Numpad8 => KeyCode::Char('8'),
+        Key9 | Numpad9 => KeyCode::Char('9'),
+        Up => KeyCode::Up,
+        Down => KeyCode::Down,
+        Left => KeyCode::Left,
+        Right => KeyCode::Right,
+        Space => KeyCode::Space,
+        Return | NumpadEnter => KeyCode::Enter,
+        Escape => KeyCode::Escape,
+        Tab => KeyCode::Tab,
+        Back => KeyCode::Backspace,
         unhandled => {
             eprintln!("Unhandled key: {:?}", unhandled);
-            None
+            return None;
         }
+    };
+    Some(code)
+}
+
+fn translate_mods(mods: KeyMods) -> Modifiers {
+    Modifiers {
+        shift: mods.contains(KeyMods::SHIFT),
+        ctrl: mods.contains(KeyMods::CTRL),
+        alt: mods.contains(KeyMods::ALT),
     }
 }